@@ -284,11 +284,13 @@ impl framework::Example for Example {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
+                constants: &[],
                 buffers: &vertex_buffers,
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main",
+                constants: &[],
                 targets: &[sc_desc.format.into()],
             }),
             primitive: wgpu::PrimitiveState {
@@ -309,11 +311,13 @@ impl framework::Example for Example {
                 vertex: wgpu::VertexState {
                     module: &shader,
                     entry_point: "vs_main",
+                    constants: &[],
                     buffers: &vertex_buffers,
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: "fs_wire",
+                    constants: &[],
                     targets: &[wgpu::ColorTargetState {
                         format: sc_desc.format,
                         blend: Some(wgpu::BlendState {
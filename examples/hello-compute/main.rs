@@ -132,6 +132,7 @@ async fn execute_gpu(numbers: Vec<u32>) -> Vec<u32> {
         layout: Some(&pipeline_layout),
         module: &cs_module,
         entry_point: "main",
+        constants: &[],
     });
 
     // A command encoder executes one or many pipelines.
@@ -52,11 +52,13 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
+            constants: &[],
             buffers: &[],
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
             entry_point: "fs_main",
+            constants: &[],
             targets: &[swapchain_format.into()],
         }),
         primitive: wgpu::PrimitiveState::default(),
@@ -479,6 +479,7 @@ impl framework::Example for Example {
                 vertex: wgpu::VertexState {
                     module: &shader,
                     entry_point: "vs_bake",
+                    constants: &[],
                     buffers: &[vb_desc.clone()],
                 },
                 fragment: None,
@@ -607,11 +608,13 @@ impl framework::Example for Example {
                 vertex: wgpu::VertexState {
                     module: &shader,
                     entry_point: "vs_main",
+                    constants: &[],
                     buffers: &[vb_desc],
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: "fs_main",
+                    constants: &[],
                     targets: &[sc_desc.format.into()],
                 }),
                 primitive: wgpu::PrimitiveState {
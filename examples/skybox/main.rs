@@ -192,11 +192,13 @@ impl framework::Example for Skybox {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_sky",
+                constants: &[],
                 buffers: &[],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_sky",
+                constants: &[],
                 targets: &[sc_desc.format.into()],
             }),
             primitive: wgpu::PrimitiveState {
@@ -219,6 +221,7 @@ impl framework::Example for Skybox {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_entity",
+                constants: &[],
                 buffers: &[wgpu::VertexBufferLayout {
                     array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
                     step_mode: wgpu::InputStepMode::Vertex,
@@ -228,6 +231,7 @@ impl framework::Example for Skybox {
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_entity",
+                constants: &[],
                 targets: &[sc_desc.format.into()],
             }),
             primitive: wgpu::PrimitiveState {
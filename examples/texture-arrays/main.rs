@@ -257,6 +257,7 @@ impl framework::Example for Example {
             vertex: wgpu::VertexState {
                 module: &vs_module,
                 entry_point: "main",
+                constants: &[],
                 buffers: &[wgpu::VertexBufferLayout {
                     array_stride: vertex_size as wgpu::BufferAddress,
                     step_mode: wgpu::InputStepMode::Vertex,
@@ -266,6 +267,7 @@ impl framework::Example for Example {
             fragment: Some(wgpu::FragmentState {
                 module: &fs_module,
                 entry_point: "main",
+                constants: &[],
                 targets: &[sc_desc.format.into()],
             }),
             primitive: wgpu::PrimitiveState {
@@ -505,6 +505,7 @@ impl framework::Example for Example {
             vertex: wgpu::VertexState {
                 module: &water_vs_module,
                 entry_point: "main",
+                constants: &[],
                 // Layout of our vertices. This should match the structs
                 // which are uploaded to the GPU. This should also be
                 // ensured by tagging on either a `#[repr(C)]` onto a
@@ -520,6 +521,7 @@ impl framework::Example for Example {
             fragment: Some(wgpu::FragmentState {
                 module: &water_fs_module,
                 entry_point: "main",
+                constants: &[],
                 // Describes how the colour will be interpolated
                 // and assigned to the output attachment.
                 targets: &[wgpu::ColorTargetState {
@@ -574,6 +576,7 @@ impl framework::Example for Example {
             vertex: wgpu::VertexState {
                 module: &terrain_vs_module,
                 entry_point: "main",
+                constants: &[],
                 buffers: &[wgpu::VertexBufferLayout {
                     array_stride: terrain_vertex_size as wgpu::BufferAddress,
                     step_mode: wgpu::InputStepMode::Vertex,
@@ -583,6 +586,7 @@ impl framework::Example for Example {
             fragment: Some(wgpu::FragmentState {
                 module: &terrain_fs_module,
                 entry_point: "main",
+                constants: &[],
                 targets: &[sc_desc.format.into()],
             }),
             primitive: wgpu::PrimitiveState {
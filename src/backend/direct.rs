@@ -1,29 +1,102 @@
 use crate::{
-    backend::{error::ContextError, native_gpu_future},
+    backend::{error::ContextError, native_gpu_future, specialize},
     AdapterInfo, BindGroupDescriptor, BindGroupLayoutDescriptor, BindingResource,
     CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor, Features, Label,
-    Limits, LoadOp, MapMode, Operations, PipelineLayoutDescriptor, RenderBundleEncoderDescriptor,
-    RenderPipelineDescriptor, SamplerDescriptor, ShaderModuleDescriptor, ShaderSource,
-    SwapChainStatus, TextureDescriptor, TextureFormat, TextureViewDescriptor,
+    Limits, LoadOp, MapMode, Operations, PipelineConstantId, PipelineLayoutDescriptor,
+    RenderBundleEncoderDescriptor, RenderPipelineDescriptor, SamplerDescriptor, ShaderFlags,
+    ShaderModuleDescriptor, ShaderSource, SwapChainStatus, TextureDescriptor, TextureFormat,
+    TextureViewDescriptor,
 };
 
 use arrayvec::ArrayVec;
 use parking_lot::Mutex;
 use smallvec::SmallVec;
 use std::{
-    borrow::Cow::Borrowed,
+    borrow::Cow::{Borrowed, Owned},
+    collections::HashMap,
     error::Error,
     fmt,
     future::{ready, Ready},
     marker::PhantomData,
     ops::Range,
+    panic::{self, AssertUnwindSafe},
     slice,
-    sync::Arc,
+    sync::{mpsc, Arc},
+    thread,
 };
 
 const LABEL: &str = "label";
 
-pub struct Context(wgc::hub::Global<wgc::hub::IdentityManagerFactory>);
+pub struct Context {
+    global: wgc::hub::Global<wgc::hub::IdentityManagerFactory>,
+    /// SPIR-V of the shader modules declaring specialization constants, kept to specialize
+    /// pipeline-overridable constants.
+    spirv_modules: Mutex<HashMap<wgc::id::ShaderModuleId, (Vec<u32>, ShaderFlags)>>,
+    pipeline_workers: PipelineWorkers,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads creating the pipelines requested with the `*_async` methods.
+///
+/// They are spawned on first use, and stop when the context is dropped.
+#[derive(Default)]
+struct PipelineWorkers {
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+}
+
+impl PipelineWorkers {
+    const COUNT: usize = 2;
+
+    fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        let mut sender = self.sender.lock();
+        let sender = sender.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<Job>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            for index in 0..Self::COUNT {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("wgpu pipeline worker {}", index))
+                    .spawn(move || loop {
+                        let job = receiver.lock().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("Failed to spawn a pipeline worker thread");
+            }
+            sender
+        });
+        // The workers only stop once the sender is dropped
+        sender.send(Box::new(job)).unwrap();
+    }
+}
+
+/// Panic raised while creating a pipeline on a worker thread.
+#[derive(Debug)]
+struct PipelineWorkerPanic(String);
+
+impl PipelineWorkerPanic {
+    fn new(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => String::new(),
+            },
+        };
+        Self(message)
+    }
+}
+
+impl fmt::Display for PipelineWorkerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pipeline creation panicked: {}", self.0)
+    }
+}
+
+impl Error for PipelineWorkerPanic {}
 
 impl Drop for Context {
     fn drop(&mut self) {
@@ -39,11 +112,11 @@ impl fmt::Debug for Context {
 
 impl Context {
     pub(crate) fn global(&self) -> &wgc::hub::Global<wgc::hub::IdentityManagerFactory> {
-        &self.0
+        &self.global
     }
 
     pub fn enumerate_adapters(&self, backends: wgt::BackendBit) -> Vec<wgc::id::AdapterId> {
-        self.0
+        self.global
             .enumerate_adapters(wgc::instance::AdapterInputs::Mask(backends, |_| {
                 PhantomData
             }))
@@ -58,15 +131,15 @@ impl Context {
             #[cfg(feature = "vulkan-portability")]
             vulkan: None, //TODO: create_surface_from_layer ?
             metal: self
-                .0
+                .global
                 .instance
                 .metal
                 .as_ref()
                 .map(|inst| inst.create_surface_from_layer(std::mem::transmute(layer))),
         };
 
-        let id = self.0.surfaces.process_id(PhantomData);
-        self.0
+        let id = self.global.surfaces.process_id(PhantomData);
+        self.global
             .surfaces
             .register(id, surface, &mut wgc::hub::Token::root());
         crate::Surface {
@@ -75,29 +148,27 @@ impl Context {
         }
     }
 
-    fn handle_error(
+    fn make_error(
         &self,
-        sink_mutex: &Mutex<ErrorSinkRaw>,
         cause: impl Error + Send + Sync + 'static,
         label_key: &'static str,
         label: Label,
         string: &'static str,
-    ) {
+    ) -> crate::Error {
         let error = ContextError {
             string,
             cause: Box::new(cause),
             label: label.unwrap_or_default().to_string(),
             label_key,
         };
-        let sink = sink_mutex.lock();
         let mut source_opt: Option<&(dyn Error + 'static)> = Some(&error);
         while let Some(source) = source_opt {
             if let Some(device_error) = source.downcast_ref::<wgc::device::DeviceError>() {
                 match device_error {
                     wgc::device::DeviceError::OutOfMemory => {
-                        return sink.handle_error(crate::Error::OutOfMemoryError {
+                        return crate::Error::OutOfMemoryError {
                             source: Box::new(error),
-                        });
+                        };
                     }
                     _ => {}
                 }
@@ -106,10 +177,221 @@ impl Context {
         }
 
         // Otherwise, it is a validation error
-        sink.handle_error(crate::Error::ValidationError {
+        crate::Error::ValidationError {
             description: self.format_error(&error),
             source: Box::new(error),
-        });
+        }
+    }
+
+    fn handle_error(
+        &self,
+        sink_mutex: &Mutex<ErrorSinkRaw>,
+        cause: impl Error + Send + Sync + 'static,
+        label_key: &'static str,
+        label: Label,
+        string: &'static str,
+    ) {
+        let error = self.make_error(cause, label_key, label, string);
+        sink_mutex.lock().handle_error(error);
+    }
+
+    /// Create a copy of a SPIR-V module with its pipeline-overridable constants specialized.
+    ///
+    /// Returns `None` if there is nothing to specialize, or if it failed. The error is returned
+    /// alongside, for the caller to report, and the pipeline gets created from the original
+    /// module.
+    fn specialize_module(
+        &self,
+        device: &Device,
+        module: wgc::id::ShaderModuleId,
+        constants: &[(PipelineConstantId, f64)],
+        label: Label,
+        string: &'static str,
+    ) -> (Option<wgc::id::ShaderModuleId>, Option<crate::Error>) {
+        if constants.is_empty() {
+            return (None, None);
+        }
+        let specialized = match self.spirv_modules.lock().get(&module) {
+            Some(&(ref words, flags)) => {
+                specialize::specialize(words, constants).map(|words| (words, flags))
+            }
+            None => Err(specialize::SpecializationError::NoSpecConstants),
+        };
+        let (words, flags) = match specialized {
+            Ok(specialized) => specialized,
+            Err(cause) => return (None, Some(self.make_error(cause, LABEL, label, string))),
+        };
+
+        let global = &self.global;
+        let descriptor = wgc::pipeline::ShaderModuleDescriptor {
+            label: label.map(Borrowed),
+            flags,
+        };
+        let source = wgc::pipeline::ShaderModuleSource::SpirV(Owned(words));
+        let (id, error) = wgc::gfx_select!(
+            device.id => global.device_create_shader_module(device.id, &descriptor, source, PhantomData)
+        );
+        let error = error.map(|cause| self.make_error(cause, LABEL, label, string));
+        (Some(id), error)
+    }
+
+    fn drop_shader_modules(&self, modules: &[wgc::id::ShaderModuleId]) {
+        let global = &self.global;
+        for &module in modules {
+            wgc::gfx_select!(module => global.shader_module_drop(module));
+        }
+    }
+
+    /// Translate a render pipeline descriptor to an owned one, so that the pipeline can
+    /// also be created on a worker thread.
+    ///
+    /// Also returns the specialized shader modules, to drop once the pipeline is created,
+    /// and the first specialization error.
+    fn map_render_pipeline_descriptor(
+        &self,
+        device: &Device,
+        desc: &RenderPipelineDescriptor,
+        string: &'static str,
+    ) -> (
+        wgc::pipeline::RenderPipelineDescriptor<'static>,
+        ArrayVec<[wgc::id::ShaderModuleId; 2]>,
+        Option<crate::Error>,
+    ) {
+        use wgc::pipeline as pipe;
+
+        let vertex_buffers = desc
+            .vertex
+            .buffers
+            .iter()
+            .map(|vbuf| pipe::VertexBufferLayout {
+                array_stride: vbuf.array_stride,
+                step_mode: vbuf.step_mode,
+                attributes: Owned(vbuf.attributes.to_vec()),
+            })
+            .collect::<Vec<_>>();
+
+        let (vertex_module, vertex_error) = self.specialize_module(
+            device,
+            desc.vertex.module.id,
+            desc.vertex.constants,
+            desc.label,
+            string,
+        );
+        let (fragment_module, fragment_error) = match desc.fragment {
+            Some(ref frag) => {
+                self.specialize_module(device, frag.module.id, frag.constants, desc.label, string)
+            }
+            None => (None, None),
+        };
+        let descriptor = pipe::RenderPipelineDescriptor {
+            label: desc.label.map(|label| Owned(label.to_string())),
+            layout: desc.layout.map(|l| l.id),
+            vertex: pipe::VertexState {
+                stage: pipe::ProgrammableStageDescriptor {
+                    module: vertex_module.unwrap_or(desc.vertex.module.id),
+                    entry_point: Owned(desc.vertex.entry_point.to_string()),
+                },
+                buffers: Owned(vertex_buffers),
+            },
+            primitive: desc.primitive.clone(),
+            depth_stencil: desc.depth_stencil.clone(),
+            multisample: desc.multisample.clone(),
+            fragment: desc.fragment.as_ref().map(|frag| pipe::FragmentState {
+                stage: pipe::ProgrammableStageDescriptor {
+                    module: fragment_module.unwrap_or(frag.module.id),
+                    entry_point: Owned(frag.entry_point.to_string()),
+                },
+                targets: Owned(frag.targets.to_vec()),
+            }),
+        };
+        let specialized = vertex_module.into_iter().chain(fragment_module).collect();
+        (descriptor, specialized, vertex_error.or(fragment_error))
+    }
+
+    fn create_render_pipeline_raw(
+        &self,
+        device: wgc::id::DeviceId,
+        descriptor: &wgc::pipeline::RenderPipelineDescriptor,
+        specialized: &[wgc::id::ShaderModuleId],
+    ) -> (
+        wgc::id::RenderPipelineId,
+        Option<wgc::pipeline::CreateRenderPipelineError>,
+    ) {
+        let implicit_pipeline_ids = match descriptor.layout {
+            Some(_) => None,
+            None => Some(wgc::device::ImplicitPipelineIds {
+                root_id: PhantomData,
+                group_ids: &[PhantomData; wgc::MAX_BIND_GROUPS],
+            }),
+        };
+        let global = &self.global;
+        let (id, _, error) = wgc::gfx_select!(device => global.device_create_render_pipeline(
+            device,
+            descriptor,
+            PhantomData,
+            implicit_pipeline_ids
+        ));
+        // The pipeline keeps the specialized modules alive
+        self.drop_shader_modules(specialized);
+        (id, error)
+    }
+
+    /// Translate a compute pipeline descriptor to an owned one, so that the pipeline can
+    /// also be created on a worker thread.
+    ///
+    /// Also returns the specialized shader module, to drop once the pipeline is created,
+    /// and the specialization error.
+    fn map_compute_pipeline_descriptor(
+        &self,
+        device: &Device,
+        desc: &ComputePipelineDescriptor,
+        string: &'static str,
+    ) -> (
+        wgc::pipeline::ComputePipelineDescriptor<'static>,
+        Option<wgc::id::ShaderModuleId>,
+        Option<crate::Error>,
+    ) {
+        use wgc::pipeline as pipe;
+
+        let (module, error) =
+            self.specialize_module(device, desc.module.id, desc.constants, desc.label, string);
+        let descriptor = pipe::ComputePipelineDescriptor {
+            label: desc.label.map(|label| Owned(label.to_string())),
+            layout: desc.layout.map(|l| l.id),
+            stage: pipe::ProgrammableStageDescriptor {
+                module: module.unwrap_or(desc.module.id),
+                entry_point: Owned(desc.entry_point.to_string()),
+            },
+        };
+        (descriptor, module, error)
+    }
+
+    fn create_compute_pipeline_raw(
+        &self,
+        device: wgc::id::DeviceId,
+        descriptor: &wgc::pipeline::ComputePipelineDescriptor,
+        specialized: Option<wgc::id::ShaderModuleId>,
+    ) -> (
+        wgc::id::ComputePipelineId,
+        Option<wgc::pipeline::CreateComputePipelineError>,
+    ) {
+        let implicit_pipeline_ids = match descriptor.layout {
+            Some(_) => None,
+            None => Some(wgc::device::ImplicitPipelineIds {
+                root_id: PhantomData,
+                group_ids: &[PhantomData; wgc::MAX_BIND_GROUPS],
+            }),
+        };
+        let global = &self.global;
+        let (id, _, error) = wgc::gfx_select!(device => global.device_create_compute_pipeline(
+            device,
+            descriptor,
+            PhantomData,
+            implicit_pipeline_ids
+        ));
+        // The pipeline keeps the specialized module alive
+        self.drop_shader_modules(specialized.as_ref().map_or(&[], slice::from_ref));
+        (id, error)
     }
 
     fn handle_error_nolabel(
@@ -132,6 +414,7 @@ impl Context {
 
 mod pass_impl {
     use super::Context;
+    use crate::util::{DrawIndexedIndirectArgs, DrawIndirectArgs};
     use smallvec::SmallVec;
     use std::convert::TryInto;
     use std::ops::Range;
@@ -512,19 +795,32 @@ mod pass_impl {
         }
         fn multi_draw_indirect(
             &mut self,
-            _indirect_buffer: &super::Buffer,
-            _indirect_offset: wgt::BufferAddress,
-            _count: u32,
+            indirect_buffer: &super::Buffer,
+            indirect_offset: wgt::BufferAddress,
+            count: u32,
         ) {
-            unimplemented!()
+            // Bundles don't record multi-draws, so they are expanded into tightly packed draws
+            for index in 0..count as wgt::BufferAddress {
+                wgpu_render_bundle_draw_indirect(
+                    self,
+                    indirect_buffer.id,
+                    indirect_offset + index * DrawIndirectArgs::SIZE,
+                )
+            }
         }
         fn multi_draw_indexed_indirect(
             &mut self,
-            _indirect_buffer: &super::Buffer,
-            _indirect_offset: wgt::BufferAddress,
-            _count: u32,
+            indirect_buffer: &super::Buffer,
+            indirect_offset: wgt::BufferAddress,
+            count: u32,
         ) {
-            unimplemented!()
+            for index in 0..count as wgt::BufferAddress {
+                wgpu_render_pass_bundle_indexed_indirect(
+                    self,
+                    indirect_buffer.id,
+                    indirect_offset + index * DrawIndexedIndirectArgs::SIZE,
+                )
+            }
         }
         fn multi_draw_indirect_count(
             &mut self,
@@ -534,7 +830,7 @@ mod pass_impl {
             _count_buffer_offset: wgt::BufferAddress,
             _max_count: u32,
         ) {
-            unimplemented!()
+            unreachable!("Render bundles don't expose multi_draw_indirect_count")
         }
         fn multi_draw_indexed_indirect_count(
             &mut self,
@@ -544,7 +840,7 @@ mod pass_impl {
             _count_buffer_offset: wgt::BufferAddress,
             _max_count: u32,
         ) {
-            unimplemented!()
+            unreachable!("Render bundles don't expose multi_draw_indexed_indirect_count")
         }
     }
 }
@@ -658,27 +954,32 @@ impl crate::Context for Context {
     type RequestDeviceFuture =
         Ready<Result<(Self::DeviceId, Self::QueueId), crate::RequestDeviceError>>;
     type MapAsyncFuture = native_gpu_future::GpuFuture<Result<(), crate::BufferAsyncError>>;
+    type PopErrorScopeFuture = Ready<Option<crate::Error>>;
+    type CreateRenderPipelineFuture =
+        native_gpu_future::GpuFuture<Result<Self::RenderPipelineId, crate::Error>>;
+    type CreateComputePipelineFuture =
+        native_gpu_future::GpuFuture<Result<Self::ComputePipelineId, crate::Error>>;
 
     fn init(backends: wgt::BackendBit) -> Self {
-        Self(wgc::hub::Global::new(
-            "wgpu",
-            wgc::hub::IdentityManagerFactory,
-            backends,
-        ))
+        Self {
+            global: wgc::hub::Global::new("wgpu", wgc::hub::IdentityManagerFactory, backends),
+            spirv_modules: Mutex::default(),
+            pipeline_workers: PipelineWorkers::default(),
+        }
     }
 
     fn instance_create_surface(
         &self,
         handle: &impl raw_window_handle::HasRawWindowHandle,
     ) -> Self::SurfaceId {
-        self.0.instance_create_surface(handle, PhantomData)
+        self.global.instance_create_surface(handle, PhantomData)
     }
 
     fn instance_request_adapter(
         &self,
         options: &crate::RequestAdapterOptions,
     ) -> Self::RequestAdapterFuture {
-        let id = self.0.request_adapter(
+        let id = self.global.request_adapter(
             &wgc::instance::RequestAdapterOptions {
                 power_preference: options.power_preference,
                 compatible_surface: options.compatible_surface.map(|surface| surface.id),
@@ -689,7 +990,7 @@ impl crate::Context for Context {
     }
 
     fn instance_poll_all_devices(&self, force_wait: bool) {
-        let global = &self.0;
+        let global = &self.global;
         match global.poll_all_devices(force_wait) {
             Ok(()) => (),
             Err(err) => self.handle_error_fatal(err, "Device::poll"),
@@ -702,7 +1003,7 @@ impl crate::Context for Context {
         desc: &crate::DeviceDescriptor,
         trace_dir: Option<&std::path::Path>,
     ) -> Self::RequestDeviceFuture {
-        let global = &self.0;
+        let global = &self.global;
         let (device_id, error) = wgc::gfx_select!(*adapter => global.adapter_request_device(
             *adapter,
             &desc.map_label(|l| l.map(Borrowed)),
@@ -725,7 +1026,7 @@ impl crate::Context for Context {
         adapter: &Self::AdapterId,
         surface: &Self::SurfaceId,
     ) -> TextureFormat {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(adapter => global.adapter_get_swap_chain_preferred_format(*adapter, *surface))
         {
             Ok(swap_chain_preferred_format) => swap_chain_preferred_format,
@@ -734,7 +1035,7 @@ impl crate::Context for Context {
     }
 
     fn adapter_features(&self, adapter: &Self::AdapterId) -> Features {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*adapter => global.adapter_features(*adapter)) {
             Ok(features) => features,
             Err(err) => self.handle_error_fatal(err, "Adapter::features"),
@@ -742,7 +1043,7 @@ impl crate::Context for Context {
     }
 
     fn adapter_limits(&self, adapter: &Self::AdapterId) -> Limits {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*adapter => global.adapter_limits(*adapter)) {
             Ok(limits) => limits,
             Err(err) => self.handle_error_fatal(err, "Adapter::limits"),
@@ -750,7 +1051,7 @@ impl crate::Context for Context {
     }
 
     fn adapter_get_info(&self, adapter: &wgc::id::AdapterId) -> AdapterInfo {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*adapter => global.adapter_get_info(*adapter)) {
             Ok(info) => info,
             Err(err) => self.handle_error_fatal(err, "Adapter::get_info"),
@@ -762,7 +1063,7 @@ impl crate::Context for Context {
         adapter: &Self::AdapterId,
         format: wgt::TextureFormat,
     ) -> wgt::TextureFormatFeatures {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*adapter => global.adapter_get_texture_format_features(*adapter, format))
         {
             Ok(info) => info,
@@ -771,7 +1072,7 @@ impl crate::Context for Context {
     }
 
     fn device_features(&self, device: &Self::DeviceId) -> Features {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(device.id => global.device_features(device.id)) {
             Ok(features) => features,
             Err(err) => self.handle_error_fatal(err, "Device::features"),
@@ -779,7 +1080,7 @@ impl crate::Context for Context {
     }
 
     fn device_limits(&self, device: &Self::DeviceId) -> Limits {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(device.id => global.device_limits(device.id)) {
             Ok(limits) => limits,
             Err(err) => self.handle_error_fatal(err, "Device::limits"),
@@ -792,7 +1093,7 @@ impl crate::Context for Context {
         surface: &Self::SurfaceId,
         desc: &wgt::SwapChainDescriptor,
     ) -> Self::SwapChainId {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(
             device.id => global.device_create_swap_chain(device.id, *surface, desc)
         ) {
@@ -806,7 +1107,7 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &ShaderModuleDescriptor,
     ) -> Self::ShaderModuleId {
-        let global = &self.0;
+        let global = &self.global;
         let descriptor = wgc::pipeline::ShaderModuleDescriptor {
             label: desc.label.map(Borrowed),
             flags: desc.flags,
@@ -814,18 +1115,53 @@ impl crate::Context for Context {
         let source = match desc.source {
             ShaderSource::SpirV(ref spv) => wgc::pipeline::ShaderModuleSource::SpirV(Borrowed(spv)),
             ShaderSource::Wgsl(ref code) => wgc::pipeline::ShaderModuleSource::Wgsl(Borrowed(code)),
+            ShaderSource::Glsl {
+                ref code,
+                stage,
+                ref defines,
+            } => {
+                let string = "Device::create_shader_module";
+                let parsed = match super::glsl_options(stage, defines) {
+                    Ok(options) => naga::front::glsl::parse_str(code, &options)
+                        .map_err(|cause| self.make_error(cause, LABEL, desc.label, string)),
+                    Err(cause) => Err(self.make_error(cause, LABEL, desc.label, string)),
+                };
+                match parsed {
+                    Ok(module) => wgc::pipeline::ShaderModuleSource::Naga(module),
+                    Err(error) => {
+                        device.error_sink.lock().handle_error(error);
+                        // Keep going with an empty module, so that the returned ID is valid
+                        // and pipelines using it fail validation instead.
+                        wgc::pipeline::ShaderModuleSource::Naga(naga::Module::default())
+                    }
+                }
+            }
+            ShaderSource::Naga(ref module) => {
+                wgc::pipeline::ShaderModuleSource::Naga(module.clone())
+            }
         };
         let (id, error) = wgc::gfx_select!(
             device.id => global.device_create_shader_module(device.id, &descriptor, source, PhantomData)
         );
-        if let Some(cause) = error {
-            self.handle_error(
+        match error {
+            Some(cause) => self.handle_error(
                 &device.error_sink,
                 cause,
                 LABEL,
                 desc.label,
                 "Device::create_shader_module",
-            );
+            ),
+            // Only valid modules with specialization constants can be specialized, so the
+            // words of the others aren't kept
+            None => {
+                if let ShaderSource::SpirV(ref spv) = desc.source {
+                    if specialize::has_spec_constants(spv) {
+                        self.spirv_modules
+                            .lock()
+                            .insert(id, (spv.to_vec(), desc.flags));
+                    }
+                }
+            }
         }
         id
     }
@@ -835,7 +1171,7 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &BindGroupLayoutDescriptor,
     ) -> Self::BindGroupLayoutId {
-        let global = &self.0;
+        let global = &self.global;
         let descriptor = wgc::binding_model::BindGroupLayoutDescriptor {
             label: desc.label.map(Borrowed),
             entries: Borrowed(desc.entries),
@@ -914,7 +1250,7 @@ impl crate::Context for Context {
             entries: Borrowed(&entries),
         };
 
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_bind_group(
             device.id,
             &descriptor,
@@ -959,7 +1295,7 @@ impl crate::Context for Context {
             push_constant_ranges: Borrowed(&desc.push_constant_ranges),
         };
 
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_pipeline_layout(
             device.id,
             &descriptor,
@@ -983,55 +1319,13 @@ impl crate::Context for Context {
         desc: &RenderPipelineDescriptor,
     ) -> Self::RenderPipelineId {
         wgc::span!(_guard, TRACE, "Device::create_render_pipeline wrapper");
-        use wgc::pipeline as pipe;
-
-        let vertex_buffers: ArrayVec<[_; wgc::device::MAX_VERTEX_BUFFERS]> = desc
-            .vertex
-            .buffers
-            .iter()
-            .map(|vbuf| pipe::VertexBufferLayout {
-                array_stride: vbuf.array_stride,
-                step_mode: vbuf.step_mode,
-                attributes: Borrowed(vbuf.attributes),
-            })
-            .collect();
 
-        let implicit_pipeline_ids = match desc.layout {
-            Some(_) => None,
-            None => Some(wgc::device::ImplicitPipelineIds {
-                root_id: PhantomData,
-                group_ids: &[PhantomData; wgc::MAX_BIND_GROUPS],
-            }),
-        };
-        let descriptor = pipe::RenderPipelineDescriptor {
-            label: desc.label.map(Borrowed),
-            layout: desc.layout.map(|l| l.id),
-            vertex: pipe::VertexState {
-                stage: pipe::ProgrammableStageDescriptor {
-                    module: desc.vertex.module.id,
-                    entry_point: Borrowed(desc.vertex.entry_point),
-                },
-                buffers: Borrowed(&vertex_buffers),
-            },
-            primitive: desc.primitive.clone(),
-            depth_stencil: desc.depth_stencil.clone(),
-            multisample: desc.multisample.clone(),
-            fragment: desc.fragment.as_ref().map(|frag| pipe::FragmentState {
-                stage: pipe::ProgrammableStageDescriptor {
-                    module: frag.module.id,
-                    entry_point: Borrowed(frag.entry_point),
-                },
-                targets: Borrowed(frag.targets),
-            }),
-        };
-
-        let global = &self.0;
-        let (id, _, error) = wgc::gfx_select!(device.id => global.device_create_render_pipeline(
-            device.id,
-            &descriptor,
-            PhantomData,
-            implicit_pipeline_ids
-        ));
+        let (descriptor, specialized, error) =
+            self.map_render_pipeline_descriptor(device, desc, "Device::create_render_pipeline");
+        if let Some(error) = error {
+            device.error_sink.lock().handle_error(error);
+        }
+        let (id, error) = self.create_render_pipeline_raw(device.id, &descriptor, &specialized);
         if let Some(cause) = error {
             self.handle_error(
                 &device.error_sink,
@@ -1049,31 +1343,12 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &ComputePipelineDescriptor,
     ) -> Self::ComputePipelineId {
-        use wgc::pipeline as pipe;
-
-        let implicit_pipeline_ids = match desc.layout {
-            Some(_) => None,
-            None => Some(wgc::device::ImplicitPipelineIds {
-                root_id: PhantomData,
-                group_ids: &[PhantomData; wgc::MAX_BIND_GROUPS],
-            }),
-        };
-        let descriptor = pipe::ComputePipelineDescriptor {
-            label: desc.label.map(Borrowed),
-            layout: desc.layout.map(|l| l.id),
-            stage: pipe::ProgrammableStageDescriptor {
-                module: desc.module.id,
-                entry_point: Borrowed(desc.entry_point),
-            },
-        };
-
-        let global = &self.0;
-        let (id, _, error) = wgc::gfx_select!(device.id => global.device_create_compute_pipeline(
-            device.id,
-            &descriptor,
-            PhantomData,
-            implicit_pipeline_ids
-        ));
+        let (descriptor, specialized, error) =
+            self.map_compute_pipeline_descriptor(device, desc, "Device::create_compute_pipeline");
+        if let Some(error) = error {
+            device.error_sink.lock().handle_error(error);
+        }
+        let (id, error) = self.create_compute_pipeline_raw(device.id, &descriptor, specialized);
         if let Some(cause) = error {
             self.handle_error(
                 &device.error_sink,
@@ -1086,12 +1361,88 @@ impl crate::Context for Context {
         id
     }
 
+    fn device_create_render_pipeline_async(
+        self: &Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture {
+        const STRING: &str = "Device::create_render_pipeline_async";
+        let (descriptor, specialized, error) =
+            self.map_render_pipeline_descriptor(device, desc, STRING);
+        let (future, completion) = native_gpu_future::new_gpu_future();
+        if let Some(error) = error {
+            self.drop_shader_modules(&specialized);
+            completion.complete(Err(error));
+            return future;
+        }
+        let context = Arc::clone(self);
+        let device_id = device.id;
+        self.pipeline_workers.spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                context.create_render_pipeline_raw(device_id, &descriptor, &specialized)
+            }));
+            let label = descriptor.label.as_deref();
+            completion.complete(match result {
+                Ok((id, None)) => Ok(id),
+                Ok((id, Some(cause))) => {
+                    let error = context.make_error(cause, LABEL, label, STRING);
+                    let global = &context.global;
+                    wgc::gfx_select!(id => global.render_pipeline_drop(id));
+                    Err(error)
+                }
+                Err(payload) => {
+                    let cause = PipelineWorkerPanic::new(payload);
+                    Err(context.make_error(cause, LABEL, label, STRING))
+                }
+            });
+        });
+        future
+    }
+
+    fn device_create_compute_pipeline_async(
+        self: &Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture {
+        const STRING: &str = "Device::create_compute_pipeline_async";
+        let (descriptor, specialized, error) =
+            self.map_compute_pipeline_descriptor(device, desc, STRING);
+        let (future, completion) = native_gpu_future::new_gpu_future();
+        if let Some(error) = error {
+            self.drop_shader_modules(specialized.as_ref().map_or(&[], slice::from_ref));
+            completion.complete(Err(error));
+            return future;
+        }
+        let context = Arc::clone(self);
+        let device_id = device.id;
+        self.pipeline_workers.spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                context.create_compute_pipeline_raw(device_id, &descriptor, specialized)
+            }));
+            let label = descriptor.label.as_deref();
+            completion.complete(match result {
+                Ok((id, None)) => Ok(id),
+                Ok((id, Some(cause))) => {
+                    let error = context.make_error(cause, LABEL, label, STRING);
+                    let global = &context.global;
+                    wgc::gfx_select!(id => global.compute_pipeline_drop(id));
+                    Err(error)
+                }
+                Err(payload) => {
+                    let cause = PipelineWorkerPanic::new(payload);
+                    Err(context.make_error(cause, LABEL, label, STRING))
+                }
+            });
+        });
+        future
+    }
+
     fn device_create_buffer(
         &self,
         device: &Self::DeviceId,
         desc: &crate::BufferDescriptor<'_>,
     ) -> Self::BufferId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_buffer(
             device.id,
             &desc.map_label(|l| l.map(Borrowed)),
@@ -1117,7 +1468,7 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &TextureDescriptor,
     ) -> Self::TextureId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_texture(
             device.id,
             &desc.map_label(|l| l.map(Borrowed)),
@@ -1160,7 +1511,7 @@ impl crate::Context for Context {
             border_color: desc.border_color,
         };
 
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_sampler(
             device.id,
             &descriptor,
@@ -1183,7 +1534,7 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &wgt::QuerySetDescriptor,
     ) -> Self::QuerySetId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_query_set(
             device.id,
             &desc,
@@ -1200,7 +1551,7 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &CommandEncoderDescriptor,
     ) -> Self::CommandEncoderId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(device.id => global.device_create_command_encoder(
             device.id,
             &desc.map_label(|l| l.map(Borrowed)),
@@ -1241,7 +1592,7 @@ impl crate::Context for Context {
     fn device_drop(&self, device: &Self::DeviceId) {
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let global = &self.0;
+            let global = &self.global;
             match wgc::gfx_select!(device.id => global.device_poll(device.id, true)) {
                 Ok(()) => (),
                 Err(err) => self.handle_error_fatal(err, "Device::drop"),
@@ -1251,13 +1602,13 @@ impl crate::Context for Context {
         #[cfg(not(target_arch = "wasm32"))]
         #[cfg(feature = "metal-auto-capture")]
         {
-            let global = &self.0;
+            let global = &self.global;
             wgc::gfx_select!(device.id => global.device_drop(device.id));
         }
     }
 
     fn device_poll(&self, device: &Self::DeviceId, maintain: crate::Maintain) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(device.id => global.device_poll(
             device.id,
             match maintain {
@@ -1279,6 +1630,23 @@ impl crate::Context for Context {
         error_sink.uncaptured_handler = Box::new(handler);
     }
 
+    fn device_push_error_scope(&self, device: &Self::DeviceId, filter: crate::ErrorFilter) {
+        let mut error_sink = device.error_sink.lock();
+        error_sink.scopes.push(ErrorScope {
+            error: None,
+            filter,
+        });
+    }
+
+    fn device_pop_error_scope(&self, device: &Self::DeviceId) -> Self::PopErrorScopeFuture {
+        let mut error_sink = device.error_sink.lock();
+        let scope = error_sink
+            .scopes
+            .pop()
+            .expect("Mismatched pop_error_scope call: no error scope for this device");
+        ready(scope.error)
+    }
+
     fn buffer_map_async(
         &self,
         buffer: &Self::BufferId,
@@ -1310,7 +1678,7 @@ impl crate::Context for Context {
             user_data: completion.to_raw() as _,
         };
 
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(buffer.id => global.buffer_map_async(buffer.id, range, operation)) {
             Ok(()) => (),
             Err(cause) => self.handle_error_nolabel(&buffer.error_sink, cause, "Buffer::map_async"),
@@ -1324,7 +1692,7 @@ impl crate::Context for Context {
         sub_range: Range<wgt::BufferAddress>,
     ) -> BufferMappedRange {
         let size = sub_range.end - sub_range.start;
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(buffer.id => global.buffer_get_mapped_range(
             buffer.id,
             sub_range.start,
@@ -1339,7 +1707,7 @@ impl crate::Context for Context {
     }
 
     fn buffer_unmap(&self, buffer: &Self::BufferId) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(buffer.id => global.buffer_unmap(buffer.id)) {
             Ok(()) => (),
             Err(cause) => {
@@ -1356,7 +1724,7 @@ impl crate::Context for Context {
         SwapChainStatus,
         Self::SwapChainOutputDetail,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(
             *swap_chain => global.swap_chain_get_current_texture_view(*swap_chain, PhantomData)
         ) {
@@ -1372,7 +1740,7 @@ impl crate::Context for Context {
     }
 
     fn swap_chain_present(&self, view: &Self::TextureViewId, detail: &Self::SwapChainOutputDetail) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*view => global.swap_chain_present(detail.swap_chain_id)) {
             Ok(_status) => (),
             Err(err) => self.handle_error_fatal(err, "SwapChain::present"),
@@ -1394,7 +1762,7 @@ impl crate::Context for Context {
             base_array_layer: desc.base_array_layer,
             array_layer_count: desc.array_layer_count,
         };
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(
             texture.id => global.texture_create_view(texture.id, &descriptor, PhantomData)
         );
@@ -1412,81 +1780,82 @@ impl crate::Context for Context {
 
     fn surface_drop(&self, _surface: &Self::SurfaceId) {
         //TODO: swapchain needs to hold the surface alive
-        //self.0.surface_drop(*surface)
+        //self.global.surface_drop(*surface)
     }
 
     fn adapter_drop(&self, adapter: &Self::AdapterId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*adapter => global.adapter_drop(*adapter))
     }
 
     fn buffer_destroy(&self, buffer: &Self::BufferId) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(buffer.id => global.buffer_destroy(buffer.id)) {
             Ok(()) => (),
             Err(err) => self.handle_error_fatal(err, "Buffer::destroy"),
         }
     }
     fn buffer_drop(&self, buffer: &Self::BufferId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(buffer.id => global.buffer_drop(buffer.id, false))
     }
     fn texture_destroy(&self, texture: &Self::TextureId) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(texture.id => global.texture_destroy(texture.id)) {
             Ok(()) => (),
             Err(err) => self.handle_error_fatal(err, "Texture::destroy"),
         }
     }
     fn texture_drop(&self, texture: &Self::TextureId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(texture.id => global.texture_drop(texture.id, false))
     }
     fn texture_view_drop(&self, texture_view: &Self::TextureViewId) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*texture_view => global.texture_view_drop(*texture_view, false)) {
             Ok(()) => (),
             Err(err) => self.handle_error_fatal(err, "TextureView::drop"),
         }
     }
     fn sampler_drop(&self, sampler: &Self::SamplerId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*sampler => global.sampler_drop(*sampler))
     }
     fn query_set_drop(&self, query_set: &Self::QuerySetId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*query_set => global.query_set_drop(*query_set))
     }
     fn bind_group_drop(&self, bind_group: &Self::BindGroupId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*bind_group => global.bind_group_drop(*bind_group))
     }
     fn bind_group_layout_drop(&self, bind_group_layout: &Self::BindGroupLayoutId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*bind_group_layout => global.bind_group_layout_drop(*bind_group_layout))
     }
     fn pipeline_layout_drop(&self, pipeline_layout: &Self::PipelineLayoutId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*pipeline_layout => global.pipeline_layout_drop(*pipeline_layout))
     }
     fn shader_module_drop(&self, shader_module: &Self::ShaderModuleId) {
-        let global = &self.0;
+        self.spirv_modules.lock().remove(shader_module);
+        let global = &self.global;
         wgc::gfx_select!(*shader_module => global.shader_module_drop(*shader_module))
     }
     fn command_buffer_drop(&self, command_buffer: &Self::CommandBufferId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*command_buffer => global.command_buffer_drop(*command_buffer))
     }
     fn render_bundle_drop(&self, render_bundle: &Self::RenderBundleId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*render_bundle => global.render_bundle_drop(*render_bundle))
     }
     fn compute_pipeline_drop(&self, pipeline: &Self::ComputePipelineId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*pipeline => global.compute_pipeline_drop(*pipeline))
     }
     fn render_pipeline_drop(&self, pipeline: &Self::RenderPipelineId) {
-        let global = &self.0;
+        let global = &self.global;
         wgc::gfx_select!(*pipeline => global.render_pipeline_drop(*pipeline))
     }
 
@@ -1495,7 +1864,7 @@ impl crate::Context for Context {
         pipeline: &Self::ComputePipelineId,
         index: u32,
     ) -> Self::BindGroupLayoutId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(*pipeline => global.compute_pipeline_get_bind_group_layout(*pipeline, index, PhantomData));
         if let Some(err) = error {
             panic!("Error reflecting bind group {}: {}", index, err);
@@ -1507,7 +1876,7 @@ impl crate::Context for Context {
         pipeline: &Self::RenderPipelineId,
         index: u32,
     ) -> Self::BindGroupLayoutId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(*pipeline => global.render_pipeline_get_bind_group_layout(*pipeline, index, PhantomData));
         if let Some(err) = error {
             panic!("Error reflecting bind group {}: {}", index, err);
@@ -1524,7 +1893,7 @@ impl crate::Context for Context {
         destination_offset: wgt::BufferAddress,
         copy_size: wgt::BufferAddress,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_copy_buffer_to_buffer(
             encoder.id,
             source.id,
@@ -1548,7 +1917,7 @@ impl crate::Context for Context {
         destination: crate::TextureCopyView,
         copy_size: wgt::Extent3d,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_copy_buffer_to_texture(
             encoder.id,
             &map_buffer_copy_view(source),
@@ -1570,7 +1939,7 @@ impl crate::Context for Context {
         destination: crate::BufferCopyView,
         copy_size: wgt::Extent3d,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_copy_texture_to_buffer(
             encoder.id,
             &map_texture_copy_view(source),
@@ -1592,7 +1961,7 @@ impl crate::Context for Context {
         destination: crate::TextureCopyView,
         copy_size: wgt::Extent3d,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_copy_texture_to_texture(
             encoder.id,
             &map_texture_copy_view(source),
@@ -1613,7 +1982,7 @@ impl crate::Context for Context {
         query_set: &Self::QuerySetId,
         query_index: u32,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_write_timestamp(
             encoder.id,
             *query_set,
@@ -1636,7 +2005,7 @@ impl crate::Context for Context {
         destination: &Self::BufferId,
         destination_offset: wgt::BufferAddress,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_resolve_query_set(
             encoder.id,
             *query_set,
@@ -1671,7 +2040,7 @@ impl crate::Context for Context {
         encoder: &Self::CommandEncoderId,
         pass: &mut Self::ComputePassId,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(
             encoder.id => global.command_encoder_run_compute_pass(encoder.id, pass)
         ) {
@@ -1725,7 +2094,7 @@ impl crate::Context for Context {
         encoder: &Self::CommandEncoderId,
         pass: &mut Self::RenderPassId,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) =
             wgc::gfx_select!(encoder.id => global.command_encoder_run_render_pass(encoder.id, pass))
         {
@@ -1742,7 +2111,7 @@ impl crate::Context for Context {
 
     fn command_encoder_finish(&self, encoder: &Self::CommandEncoderId) -> Self::CommandBufferId {
         let descriptor = wgt::CommandBufferDescriptor::default();
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) =
             wgc::gfx_select!(encoder.id => global.command_encoder_finish(encoder.id, &descriptor));
         if let Some(cause) = error {
@@ -1752,7 +2121,7 @@ impl crate::Context for Context {
     }
 
     fn command_encoder_insert_debug_marker(&self, encoder: &Self::CommandEncoderId, label: &str) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_insert_debug_marker(encoder.id, &label))
         {
             self.handle_error_nolabel(
@@ -1763,7 +2132,7 @@ impl crate::Context for Context {
         }
     }
     fn command_encoder_push_debug_group(&self, encoder: &Self::CommandEncoderId, label: &str) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_push_debug_group(encoder.id, &label))
         {
             self.handle_error_nolabel(
@@ -1774,7 +2143,7 @@ impl crate::Context for Context {
         }
     }
     fn command_encoder_pop_debug_group(&self, encoder: &Self::CommandEncoderId) {
-        let global = &self.0;
+        let global = &self.global;
         if let Err(cause) =
             wgc::gfx_select!(encoder.id => global.command_encoder_pop_debug_group(encoder.id))
         {
@@ -1791,7 +2160,7 @@ impl crate::Context for Context {
         encoder: Self::RenderBundleEncoderId,
         desc: &crate::RenderBundleDescriptor,
     ) -> Self::RenderBundleId {
-        let global = &self.0;
+        let global = &self.global;
         let (id, error) = wgc::gfx_select!(encoder.parent() => global.render_bundle_encoder_finish(
             encoder,
             &desc.map_label(|l| l.map(Borrowed)),
@@ -1810,7 +2179,7 @@ impl crate::Context for Context {
         offset: wgt::BufferAddress,
         data: &[u8],
     ) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(
             *queue => global.queue_write_buffer(*queue, buffer.id, offset, data)
         ) {
@@ -1827,7 +2196,7 @@ impl crate::Context for Context {
         data_layout: wgt::TextureDataLayout,
         size: wgt::Extent3d,
     ) {
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*queue => global.queue_write_texture(
             *queue,
             &map_texture_copy_view(texture),
@@ -1847,7 +2216,7 @@ impl crate::Context for Context {
     ) {
         let temp_command_buffers = command_buffers.collect::<SmallVec<[_; 4]>>();
 
-        let global = &self.0;
+        let global = &self.global;
         match wgc::gfx_select!(*queue => global.queue_submit(*queue, &temp_command_buffers)) {
             Ok(()) => (),
             Err(err) => self.handle_error_fatal(err, "Queue::submit"),
@@ -1855,7 +2224,7 @@ impl crate::Context for Context {
     }
 
     fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32 {
-        let global = &self.0;
+        let global = &self.global;
         let res = wgc::gfx_select!(queue => global.queue_get_timestamp_period(
             *queue
         ));
@@ -1875,19 +2244,40 @@ pub(crate) struct SwapChainOutputDetail {
 
 type ErrorSink = Arc<Mutex<ErrorSinkRaw>>;
 
+struct ErrorScope {
+    error: Option<crate::Error>,
+    filter: crate::ErrorFilter,
+}
+
 struct ErrorSinkRaw {
+    scopes: Vec<ErrorScope>,
     uncaptured_handler: Box<dyn crate::UncapturedErrorHandler>,
 }
 
 impl ErrorSinkRaw {
     fn new() -> ErrorSinkRaw {
         ErrorSinkRaw {
+            scopes: Vec::new(),
             uncaptured_handler: Box::from(default_error_handler),
         }
     }
 
-    fn handle_error(&self, err: crate::Error) {
-        (self.uncaptured_handler)(err);
+    fn handle_error(&mut self, err: crate::Error) {
+        let filter = err.filter();
+        match self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.filter == filter)
+        {
+            Some(scope) => {
+                // Only the first error of a scope is reported
+                if scope.error.is_none() {
+                    scope.error = Some(err);
+                }
+            }
+            None => (self.uncaptured_handler)(err),
+        }
     }
 }
 
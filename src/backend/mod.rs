@@ -13,3 +13,39 @@ pub(crate) use direct::{BufferMappedRange, Context};
 
 #[cfg(any(not(target_arch = "wasm32"), feature = "webgl"))]
 mod native_gpu_future;
+#[cfg(any(not(target_arch = "wasm32"), feature = "webgl"))]
+mod specialize;
+
+/// `ShaderSource::Glsl` stage which isn't a single shader stage.
+#[derive(Clone, Debug)]
+pub(crate) struct InvalidGlslStage(wgt::ShaderStage);
+
+impl std::fmt::Display for InvalidGlslStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GLSL modules need a single stage, got {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidGlslStage {}
+
+/// Options of naga's GLSL frontend for a `ShaderSource::Glsl` module.
+pub(crate) fn glsl_options(
+    stage: wgt::ShaderStage,
+    defines: &std::collections::HashMap<String, String>,
+) -> Result<naga::front::glsl::Options, InvalidGlslStage> {
+    let stage = match stage {
+        wgt::ShaderStage::VERTEX => naga::ShaderStage::Vertex,
+        wgt::ShaderStage::FRAGMENT => naga::ShaderStage::Fragment,
+        wgt::ShaderStage::COMPUTE => naga::ShaderStage::Compute,
+        other => return Err(InvalidGlslStage(other)),
+    };
+    let mut options = naga::front::glsl::Options {
+        entry_points: Default::default(),
+        defines: defines
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+    };
+    options.entry_points.insert("main".to_string(), stage);
+    Ok(options)
+}
@@ -0,0 +1,290 @@
+//! Specialization of SPIR-V modules with pipeline-overridable constants.
+//!
+//! wgpu-core doesn't take specialization info, so the default values of the
+//! `OpSpecConstant*` instructions are rewritten before creating the module.
+
+use crate::PipelineConstantId;
+use std::{collections::HashMap, error::Error, fmt};
+
+const HEADER_WORDS: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_SPEC_CONSTANT_TRUE: u32 = 48;
+const OP_SPEC_CONSTANT_FALSE: u32 = 49;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_DECORATE: u32 = 71;
+const DECORATION_SPEC_ID: u32 = 1;
+
+#[derive(Clone, Debug)]
+pub(crate) enum SpecializationError {
+    NoSpecConstants,
+    Malformed,
+    UnknownConstant(String),
+    UnsupportedType(String),
+}
+
+impl fmt::Display for SpecializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSpecConstants => write!(
+                f,
+                "pipeline-overridable constants need a SPIR-V module with specialization constants"
+            ),
+            Self::Malformed => write!(f, "SPIR-V module is malformed"),
+            Self::UnknownConstant(name) => {
+                write!(f, "no specialization constant matches {}", name)
+            }
+            Self::UnsupportedType(name) => {
+                write!(
+                    f,
+                    "specialization constant {} has an unsupported type",
+                    name
+                )
+            }
+        }
+    }
+}
+
+impl Error for SpecializationError {}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    Bool,
+    Sint(u32),
+    Uint(u32),
+    Float(u32),
+}
+
+fn describe(id: PipelineConstantId) -> String {
+    match id {
+        PipelineConstantId::Name(name) => format!("{:?}", name),
+        PipelineConstantId::Id(id) => format!("ID {}", id),
+    }
+}
+
+fn decode_string(words: &[u32]) -> Option<String> {
+    // Strings are packed four bytes per word, in little-endian order.
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .take_while(|&byte| byte != 0)
+        .collect();
+    String::from_utf8(bytes).ok()
+}
+
+/// Returns `true` if `words` declare scalar specialization constants, which can be specialized.
+pub(crate) fn has_spec_constants(words: &[u32]) -> bool {
+    let mut offset = HEADER_WORDS;
+    while offset < words.len() {
+        let count = (words[offset] >> 16) as usize;
+        if count == 0 {
+            return false;
+        }
+        if let OP_SPEC_CONSTANT_TRUE | OP_SPEC_CONSTANT_FALSE | OP_SPEC_CONSTANT =
+            words[offset] & 0xFFFF
+        {
+            return true;
+        }
+        offset += count;
+    }
+    false
+}
+
+/// Return a copy of `words` where the given constants default to the given values.
+pub(crate) fn specialize(
+    words: &[u32],
+    constants: &[(PipelineConstantId, f64)],
+) -> Result<Vec<u32>, SpecializationError> {
+    if words.len() < HEADER_WORDS {
+        return Err(SpecializationError::Malformed);
+    }
+
+    let mut names = HashMap::new();
+    let mut spec_ids = HashMap::new();
+    let mut scalars = HashMap::new();
+    let mut offset = HEADER_WORDS;
+    while offset < words.len() {
+        let count = (words[offset] >> 16) as usize;
+        let op = words[offset] & 0xFFFF;
+        if count == 0 || offset + count > words.len() {
+            return Err(SpecializationError::Malformed);
+        }
+        let operands = &words[offset + 1..offset + count];
+        match (op, operands) {
+            (OP_NAME, &[target, ref name @ ..]) => {
+                if let Some(name) = decode_string(name) {
+                    names.insert(name, target);
+                }
+            }
+            (OP_DECORATE, &[target, DECORATION_SPEC_ID, spec_id]) => {
+                spec_ids.insert(spec_id, target);
+            }
+            (OP_TYPE_BOOL, &[result]) => {
+                scalars.insert(result, Scalar::Bool);
+            }
+            (OP_TYPE_INT, &[result, width, signedness]) => {
+                let scalar = if signedness != 0 {
+                    Scalar::Sint(width)
+                } else {
+                    Scalar::Uint(width)
+                };
+                scalars.insert(result, scalar);
+            }
+            (OP_TYPE_FLOAT, &[result, width, ..]) => {
+                scalars.insert(result, Scalar::Float(width));
+            }
+            _ => {}
+        }
+        offset += count;
+    }
+
+    let mut values = HashMap::new();
+    for &(id, value) in constants {
+        let target = match id {
+            PipelineConstantId::Name(name) => names.get(name),
+            PipelineConstantId::Id(id) => spec_ids.get(&id),
+        };
+        match target {
+            Some(&target) => values.insert(target, (id, value)),
+            None => return Err(SpecializationError::UnknownConstant(describe(id))),
+        };
+    }
+
+    let mut output = words.to_vec();
+    let mut offset = HEADER_WORDS;
+    while offset < output.len() {
+        let count = (output[offset] >> 16) as usize;
+        let op = output[offset] & 0xFFFF;
+        if let OP_SPEC_CONSTANT_TRUE | OP_SPEC_CONSTANT_FALSE | OP_SPEC_CONSTANT = op {
+            // The type and result, followed by the value for `OpSpecConstant`
+            let min_count = if op == OP_SPEC_CONSTANT { 4 } else { 3 };
+            if count < min_count {
+                return Err(SpecializationError::Malformed);
+            }
+            let ty = output[offset + 1];
+            let result = output[offset + 2];
+            if let Some(&(id, value)) = values.get(&result) {
+                let unsupported = || SpecializationError::UnsupportedType(describe(id));
+                let scalar = *scalars.get(&ty).ok_or_else(unsupported)?;
+                if let Scalar::Bool = scalar {
+                    if op == OP_SPEC_CONSTANT {
+                        return Err(unsupported());
+                    }
+                    let op = if value != 0.0 {
+                        OP_SPEC_CONSTANT_TRUE
+                    } else {
+                        OP_SPEC_CONSTANT_FALSE
+                    };
+                    output[offset] = (count as u32) << 16 | op;
+                    offset += count;
+                    continue;
+                }
+                match (scalar, &mut output[offset + 3..offset + count]) {
+                    (Scalar::Sint(32), &mut [ref mut word]) => *word = value as i32 as u32,
+                    (Scalar::Uint(32), &mut [ref mut word]) => *word = value as u32,
+                    (Scalar::Float(32), &mut [ref mut word]) => *word = (value as f32).to_bits(),
+                    (Scalar::Sint(64), &mut [ref mut low, ref mut high]) => {
+                        let bits = value as i64 as u64;
+                        *low = bits as u32;
+                        *high = (bits >> 32) as u32;
+                    }
+                    (Scalar::Uint(64), &mut [ref mut low, ref mut high]) => {
+                        let bits = value as u64;
+                        *low = bits as u32;
+                        *high = (bits >> 32) as u32;
+                    }
+                    (Scalar::Float(64), &mut [ref mut low, ref mut high]) => {
+                        let bits = value.to_bits();
+                        *low = bits as u32;
+                        *high = (bits >> 32) as u32;
+                    }
+                    _ => return Err(unsupported()),
+                }
+            }
+        }
+        offset += count;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+fn module(instructions: &[&[u32]]) -> Vec<u32> {
+    let mut words = vec![0x0723_0203, 0x0001_0000, 0, 16, 0];
+    for instruction in instructions {
+        words.extend_from_slice(instruction);
+    }
+    words
+}
+
+#[cfg(test)]
+fn instruction(op: u32, operands: &[u32]) -> Vec<u32> {
+    let mut words = vec![(operands.len() as u32 + 1) << 16 | op];
+    words.extend_from_slice(operands);
+    words
+}
+
+#[test]
+fn specialize_scalars() {
+    let name = u32::from_le_bytes(*b"q\0\0\0");
+    let words = module(&[
+        &instruction(OP_NAME, &[4, name]),
+        &instruction(OP_DECORATE, &[2, DECORATION_SPEC_ID, 7]),
+        &instruction(OP_TYPE_INT, &[1, 32, 0]),
+        &instruction(OP_TYPE_FLOAT, &[3, 32]),
+        &instruction(OP_SPEC_CONSTANT, &[1, 2, 64]),
+        &instruction(OP_SPEC_CONSTANT, &[3, 4, 1f32.to_bits()]),
+    ]);
+    let output = specialize(
+        &words,
+        &[
+            (PipelineConstantId::Id(7), 128.0),
+            (PipelineConstantId::Name("q"), 2.5),
+        ],
+    )
+    .unwrap();
+    assert_eq!(output.len(), words.len());
+    assert_eq!(output[words.len() - 5], 128);
+    assert_eq!(output[words.len() - 1], 2.5f32.to_bits());
+}
+
+#[test]
+fn specialize_bool() {
+    let words = module(&[
+        &instruction(OP_DECORATE, &[2, DECORATION_SPEC_ID, 0]),
+        &instruction(OP_TYPE_BOOL, &[1]),
+        &instruction(OP_SPEC_CONSTANT_TRUE, &[1, 2]),
+    ]);
+    assert!(has_spec_constants(&words));
+    assert!(!has_spec_constants(&words[..words.len() - 3]));
+    let output = specialize(&words, &[(PipelineConstantId::Id(0), 0.0)]).unwrap();
+    assert_eq!(output[words.len() - 3], 3 << 16 | OP_SPEC_CONSTANT_FALSE);
+}
+
+#[test]
+fn specialize_errors() {
+    let words = module(&[
+        &instruction(OP_DECORATE, &[2, DECORATION_SPEC_ID, 0]),
+        &instruction(OP_TYPE_INT, &[1, 32, 1]),
+        &instruction(OP_SPEC_CONSTANT, &[1, 2]),
+    ]);
+    assert!(matches!(
+        specialize(&words, &[(PipelineConstantId::Id(1), 1.0)]),
+        Err(SpecializationError::UnknownConstant(_))
+    ));
+    // The value of the constant is missing
+    assert!(matches!(
+        specialize(&words, &[(PipelineConstantId::Id(0), 1.0)]),
+        Err(SpecializationError::Malformed)
+    ));
+    assert!(matches!(
+        specialize(&words[..words.len() - 1], &[]),
+        Err(SpecializationError::Malformed)
+    ));
+    assert!(matches!(
+        specialize(&words[..3], &[]),
+        Err(SpecializationError::Malformed)
+    ));
+}
@@ -4,6 +4,7 @@ use std::{
     future::Future,
     ops::Range,
     pin::Pin,
+    sync::Arc,
     task::{self, Poll},
 };
 use wasm_bindgen::prelude::*;
@@ -734,6 +735,110 @@ fn map_vertex_state_descriptor(
     mapped
 }
 
+fn map_programmable_stage(
+    module: &crate::ShaderModule,
+    entry_point: &str,
+    constants: &[(crate::PipelineConstantId, f64)],
+) -> web_sys::GpuProgrammableStageDescriptor {
+    let mapped = web_sys::GpuProgrammableStageDescriptor::new(entry_point, &module.id.0);
+    if !constants.is_empty() {
+        // web_sys doesn't expose pipeline overrides yet, so set the record directly.
+        let record = js_sys::Object::new();
+        for &(id, value) in constants {
+            let key = match id {
+                crate::PipelineConstantId::Name(name) => name.to_string(),
+                crate::PipelineConstantId::Id(id) => id.to_string(),
+            };
+            js_sys::Reflect::set(&record, &key.into(), &value.into()).unwrap();
+        }
+        js_sys::Reflect::set(&mapped, &"constants".into(), &record).unwrap();
+    }
+    mapped
+}
+
+fn map_render_pipeline_descriptor(
+    desc: &crate::RenderPipelineDescriptor,
+) -> web_sys::GpuRenderPipelineDescriptor {
+    use web_sys::GpuPrimitiveTopology as pt;
+
+    let targets = desc.fragment.as_ref().map_or(&[][..], |frag| &frag.targets);
+    let mapped_color_states = targets
+        .iter()
+        .map(|target| {
+            let mapped_format = map_texture_format(target.format);
+            let mut mapped_color_state_desc = web_sys::GpuColorStateDescriptor::new(mapped_format);
+            if let Some(ref bs) = target.blend {
+                mapped_color_state_desc.alpha_blend(&map_blend_descriptor(&bs.alpha));
+                mapped_color_state_desc.color_blend(&map_blend_descriptor(&bs.color));
+            }
+            mapped_color_state_desc.write_mask(target.write_mask.bits());
+            mapped_color_state_desc
+        })
+        .collect::<js_sys::Array>();
+
+    let mapped_primitive_topology = match desc.primitive.topology {
+        wgt::PrimitiveTopology::PointList => pt::PointList,
+        wgt::PrimitiveTopology::LineList => pt::LineList,
+        wgt::PrimitiveTopology::LineStrip => pt::LineStrip,
+        wgt::PrimitiveTopology::TriangleList => pt::TriangleList,
+        wgt::PrimitiveTopology::TriangleStrip => pt::TriangleStrip,
+    };
+
+    let mapped_vertex_stage = map_programmable_stage(
+        desc.vertex.module,
+        desc.vertex.entry_point,
+        desc.vertex.constants,
+    );
+
+    let mut mapped_desc = web_sys::GpuRenderPipelineDescriptor::new(
+        &mapped_color_states,
+        mapped_primitive_topology,
+        &mapped_vertex_stage,
+    );
+    if let Some(layout) = desc.layout {
+        mapped_desc.layout(&layout.id.0);
+    }
+
+    // TODO: label
+
+    if let Some(ref frag) = desc.fragment {
+        let mapped_fragment_desc =
+            map_programmable_stage(frag.module, frag.entry_point, frag.constants);
+        mapped_desc.fragment_stage(&mapped_fragment_desc);
+    }
+
+    mapped_desc.rasterization_state(&map_rasterization_state_descriptor(
+        &desc.primitive,
+        desc.depth_stencil.as_ref(),
+    ));
+
+    if let Some(ref depth_stencil) = desc.depth_stencil {
+        mapped_desc.depth_stencil_state(&map_depth_stencil_state_descriptor(depth_stencil));
+    }
+
+    mapped_desc.vertex_state(&map_vertex_state_descriptor(&desc));
+    mapped_desc.sample_count(desc.multisample.count);
+    mapped_desc.sample_mask(desc.multisample.mask as u32);
+    mapped_desc.alpha_to_coverage_enabled(desc.multisample.alpha_to_coverage_enabled);
+
+    mapped_desc
+}
+
+fn map_compute_pipeline_descriptor(
+    desc: &crate::ComputePipelineDescriptor,
+) -> web_sys::GpuComputePipelineDescriptor {
+    let mapped_compute_stage =
+        map_programmable_stage(desc.module, desc.entry_point, desc.constants);
+    let mut mapped_desc = web_sys::GpuComputePipelineDescriptor::new(&mapped_compute_stage);
+    if let Some(layout) = desc.layout {
+        mapped_desc.layout(&layout.id.0);
+    }
+    if let Some(label) = desc.label {
+        mapped_desc.label(label);
+    }
+    mapped_desc
+}
+
 fn map_extent_3d(extent: wgt::Extent3d) -> web_sys::GpuExtent3dDict {
     let mut mapped = web_sys::GpuExtent3dDict::new();
     mapped.depth(extent.depth);
@@ -830,6 +935,19 @@ fn map_map_mode(mode: crate::MapMode) -> u32 {
     }
 }
 
+fn module_to_spirv(module: &naga::Module) -> Vec<u32> {
+    use naga::{back::spv, proc::Validator};
+    let mut capabilities = HashSet::default();
+    capabilities.insert(spv::Capability::Shader);
+    let options = spv::Options {
+        lang_version: (1, 0),
+        flags: spv::WriterFlags::empty(),
+        capabilities,
+    };
+    let analysis = Validator::new().validate(module).unwrap();
+    spv::write_vec(module, &analysis, &options).unwrap()
+}
+
 type JsFutureResult = Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue>;
 
 fn future_request_adapter(result: JsFutureResult) -> Option<Sendable<web_sys::GpuAdapter>> {
@@ -855,6 +973,109 @@ fn future_map_async(result: JsFutureResult) -> Result<(), crate::BufferAsyncErro
     result.map(|_| ()).map_err(|_| crate::BufferAsyncError)
 }
 
+/// Error reported by the browser through a rejected promise.
+#[derive(Debug)]
+struct JsError(String);
+
+impl fmt::Display for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for JsError {}
+
+fn map_js_error(error: wasm_bindgen::JsValue) -> crate::Error {
+    let description = match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => format!("{:?}", error),
+    };
+    crate::Error::ValidationError {
+        source: Box::new(JsError(description.clone())),
+        description,
+    }
+}
+
+fn future_create_render_pipeline(
+    result: JsFutureResult,
+) -> Result<Sendable<web_sys::GpuRenderPipeline>, crate::Error> {
+    result
+        .map(|js_value| Sendable(web_sys::GpuRenderPipeline::from(js_value)))
+        .map_err(map_js_error)
+}
+
+fn future_create_compute_pipeline(
+    result: JsFutureResult,
+) -> Result<Sendable<web_sys::GpuComputePipeline>, crate::Error> {
+    result
+        .map(|js_value| Sendable(web_sys::GpuComputePipeline::from(js_value)))
+        .map_err(map_js_error)
+}
+
+fn future_pop_error_scope(result: JsFutureResult) -> Option<crate::Error> {
+    match result {
+        Ok(error) if error.is_null() || error.is_undefined() => None,
+        Ok(error) => {
+            let class = js_sys::Reflect::get(&error, &"constructor".into())
+                .ok()
+                .and_then(|constructor| constructor.dyn_into::<js_sys::Function>().ok())
+                .map(|constructor| String::from(constructor.name()));
+            let description = js_sys::Reflect::get(&error, &"message".into())
+                .ok()
+                .and_then(|message| message.as_string())
+                .unwrap_or_default();
+            Some(if class.as_deref() == Some("GPUOutOfMemoryError") {
+                crate::Error::OutOfMemoryError {
+                    source: Box::new(JsError(description)),
+                }
+            } else {
+                crate::Error::ValidationError {
+                    source: Box::new(JsError(description.clone())),
+                    description,
+                }
+            })
+        }
+        // The scope stack was empty
+        Err(error) => Some(map_js_error(error)),
+    }
+}
+
+/// Call `method` of `device` with `args`, or return `None` if the browser doesn't implement it.
+///
+/// web_sys doesn't bind the error scope methods yet.
+fn call_device_method(
+    device: &web_sys::GpuDevice,
+    method: &str,
+    args: &[wasm_bindgen::JsValue],
+) -> Option<Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue>> {
+    let function = js_sys::Reflect::get(device, &method.into())
+        .ok()
+        .and_then(|function| function.dyn_into::<js_sys::Function>().ok())?;
+    Some(function.apply(device, &args.iter().collect::<js_sys::Array>()))
+}
+
+/// Call the asynchronous pipeline creation `method` of `device`.
+///
+/// web_sys doesn't bind these methods yet, and some browsers don't implement them,
+/// in which case the pipeline is created synchronously by `fallback`.
+fn create_pipeline_async(
+    device: &web_sys::GpuDevice,
+    method: &str,
+    desc: &wasm_bindgen::JsValue,
+    fallback: impl FnOnce() -> wasm_bindgen::JsValue,
+) -> js_sys::Promise {
+    let function = js_sys::Reflect::get(device, &method.into())
+        .ok()
+        .and_then(|function| function.dyn_into::<js_sys::Function>().ok());
+    match function {
+        Some(function) => match function.call1(device, desc) {
+            Ok(promise) => js_sys::Promise::from(promise),
+            Err(error) => js_sys::Promise::reject(&error),
+        },
+        None => js_sys::Promise::resolve(&fallback()),
+    }
+}
+
 impl crate::Context for Context {
     type AdapterId = Sendable<web_sys::GpuAdapter>;
     type DeviceId = Sendable<web_sys::GpuDevice>;
@@ -893,6 +1114,16 @@ impl crate::Context for Context {
         wasm_bindgen_futures::JsFuture,
         fn(JsFutureResult) -> Result<(), crate::BufferAsyncError>,
     >;
+    type PopErrorScopeFuture =
+        MakeSendFuture<wasm_bindgen_futures::JsFuture, fn(JsFutureResult) -> Option<crate::Error>>;
+    type CreateRenderPipelineFuture = MakeSendFuture<
+        wasm_bindgen_futures::JsFuture,
+        fn(JsFutureResult) -> Result<Self::RenderPipelineId, crate::Error>,
+    >;
+    type CreateComputePipelineFuture = MakeSendFuture<
+        wasm_bindgen_futures::JsFuture,
+        fn(JsFutureResult) -> Result<Self::ComputePipelineId, crate::Error>,
+    >;
 
     fn init(_backends: wgt::BackendBit) -> Self {
         Context(web_sys::window().unwrap().navigator().gpu())
@@ -1046,17 +1277,41 @@ impl crate::Context for Context {
                 web_sys::GpuShaderModuleDescriptor::new(&js_sys::Uint32Array::from(&**spv))
             }
             crate::ShaderSource::Wgsl(ref code) => {
-                use naga::{back::spv, front::wgsl, proc::Validator};
-                let module = wgsl::parse_str(code).unwrap();
-                let mut capabilities = HashSet::default();
-                capabilities.insert(spv::Capability::Shader);
-                let options = spv::Options {
-                    lang_version: (1, 0),
-                    flags: spv::WriterFlags::empty(),
-                    capabilities,
-                };
-                let analysis = Validator::new().validate(&module).unwrap();
-                let words = spv::write_vec(&module, &analysis, &options).unwrap();
+                let module = naga::front::wgsl::parse_str(code).unwrap();
+                let words = module_to_spirv(&module);
+                web_sys::GpuShaderModuleDescriptor::new(&js_sys::Uint32Array::from(&words[..]))
+            }
+            crate::ShaderSource::Glsl {
+                ref code,
+                stage,
+                ref defines,
+            } => {
+                let parsed = super::glsl_options(stage, defines)
+                    .map_err(|cause| cause.to_string())
+                    .and_then(|options| {
+                        naga::front::glsl::parse_str(code, &options)
+                            .map_err(|cause| cause.to_string())
+                    });
+                match parsed {
+                    Ok(module) => {
+                        let words = module_to_spirv(&module);
+                        web_sys::GpuShaderModuleDescriptor::new(&js_sys::Uint32Array::from(
+                            &words[..],
+                        ))
+                    }
+                    Err(cause) => {
+                        // The browser has no way to receive our errors, so an empty module is
+                        // created instead: it fails validation, which the browser reports
+                        // through its error scopes like any other invalid module.
+                        tracing::error!("Error in Device::create_shader_module: {}", cause);
+                        web_sys::GpuShaderModuleDescriptor::new(
+                            &js_sys::Uint32Array::new_with_length(0),
+                        )
+                    }
+                }
+            }
+            crate::ShaderSource::Naga(ref module) => {
+                let words = module_to_spirv(module);
                 web_sys::GpuShaderModuleDescriptor::new(&js_sys::Uint32Array::from(&words[..]))
             }
         };
@@ -1218,69 +1473,11 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &crate::RenderPipelineDescriptor,
     ) -> Self::RenderPipelineId {
-        use web_sys::GpuPrimitiveTopology as pt;
-
-        let targets = desc.fragment.as_ref().map_or(&[][..], |frag| &frag.targets);
-        let mapped_color_states = targets
-            .iter()
-            .map(|target| {
-                let mapped_format = map_texture_format(target.format);
-                let mut mapped_color_state_desc =
-                    web_sys::GpuColorStateDescriptor::new(mapped_format);
-                if let Some(ref bs) = target.blend {
-                    mapped_color_state_desc.alpha_blend(&map_blend_descriptor(&bs.alpha));
-                    mapped_color_state_desc.color_blend(&map_blend_descriptor(&bs.color));
-                }
-                mapped_color_state_desc.write_mask(target.write_mask.bits());
-                mapped_color_state_desc
-            })
-            .collect::<js_sys::Array>();
-
-        let mapped_primitive_topology = match desc.primitive.topology {
-            wgt::PrimitiveTopology::PointList => pt::PointList,
-            wgt::PrimitiveTopology::LineList => pt::LineList,
-            wgt::PrimitiveTopology::LineStrip => pt::LineStrip,
-            wgt::PrimitiveTopology::TriangleList => pt::TriangleList,
-            wgt::PrimitiveTopology::TriangleStrip => pt::TriangleStrip,
-        };
-
-        let mapped_vertex_stage = web_sys::GpuProgrammableStageDescriptor::new(
-            &desc.vertex.entry_point,
-            &desc.vertex.module.id.0,
-        );
-
-        let mut mapped_desc = web_sys::GpuRenderPipelineDescriptor::new(
-            &mapped_color_states,
-            mapped_primitive_topology,
-            &mapped_vertex_stage,
-        );
-        if let Some(layout) = desc.layout {
-            mapped_desc.layout(&layout.id.0);
-        }
-
-        // TODO: label
-
-        if let Some(ref frag) = desc.fragment {
-            let mapped_fragment_desc =
-                web_sys::GpuProgrammableStageDescriptor::new(&frag.entry_point, &frag.module.id.0);
-            mapped_desc.fragment_stage(&mapped_fragment_desc);
-        }
-
-        mapped_desc.rasterization_state(&map_rasterization_state_descriptor(
-            &desc.primitive,
-            desc.depth_stencil.as_ref(),
-        ));
-
-        if let Some(ref depth_stencil) = desc.depth_stencil {
-            mapped_desc.depth_stencil_state(&map_depth_stencil_state_descriptor(depth_stencil));
-        }
-
-        mapped_desc.vertex_state(&map_vertex_state_descriptor(&desc));
-        mapped_desc.sample_count(desc.multisample.count);
-        mapped_desc.sample_mask(desc.multisample.mask as u32);
-        mapped_desc.alpha_to_coverage_enabled(desc.multisample.alpha_to_coverage_enabled);
-
-        Sendable(device.0.create_render_pipeline(&mapped_desc))
+        Sendable(
+            device
+                .0
+                .create_render_pipeline(&map_render_pipeline_descriptor(desc)),
+        )
     }
 
     fn device_create_compute_pipeline(
@@ -1288,16 +1485,45 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &crate::ComputePipelineDescriptor,
     ) -> Self::ComputePipelineId {
-        let mapped_compute_stage =
-            web_sys::GpuProgrammableStageDescriptor::new(&desc.entry_point, &desc.module.id.0);
-        let mut mapped_desc = web_sys::GpuComputePipelineDescriptor::new(&mapped_compute_stage);
-        if let Some(layout) = desc.layout {
-            mapped_desc.layout(&layout.id.0);
-        }
-        if let Some(label) = desc.label {
-            mapped_desc.label(label);
-        }
-        Sendable(device.0.create_compute_pipeline(&mapped_desc))
+        Sendable(
+            device
+                .0
+                .create_compute_pipeline(&map_compute_pipeline_descriptor(desc)),
+        )
+    }
+
+    fn device_create_render_pipeline_async(
+        self: &Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &crate::RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture {
+        let mapped_desc = map_render_pipeline_descriptor(desc);
+        let promise =
+            create_pipeline_async(&device.0, "createRenderPipelineAsync", &mapped_desc, || {
+                device.0.create_render_pipeline(&mapped_desc).into()
+            });
+        MakeSendFuture::new(
+            wasm_bindgen_futures::JsFuture::from(promise),
+            future_create_render_pipeline,
+        )
+    }
+
+    fn device_create_compute_pipeline_async(
+        self: &Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &crate::ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture {
+        let mapped_desc = map_compute_pipeline_descriptor(desc);
+        let promise = create_pipeline_async(
+            &device.0,
+            "createComputePipelineAsync",
+            &mapped_desc,
+            || device.0.create_compute_pipeline(&mapped_desc).into(),
+        );
+        MakeSendFuture::new(
+            wasm_bindgen_futures::JsFuture::from(promise),
+            future_create_compute_pipeline,
+        )
     }
 
     fn device_create_buffer(
@@ -1415,6 +1641,27 @@ impl crate::Context for Context {
         // TODO:
     }
 
+    fn device_push_error_scope(&self, device: &Self::DeviceId, filter: crate::ErrorFilter) {
+        let filter = match filter {
+            crate::ErrorFilter::OutOfMemory => "out-of-memory",
+            crate::ErrorFilter::Validation => "validation",
+        };
+        // Without error scopes in the browser, the matching pop resolves to no error
+        let _ = call_device_method(&device.0, "pushErrorScope", &[filter.into()]);
+    }
+
+    fn device_pop_error_scope(&self, device: &Self::DeviceId) -> Self::PopErrorScopeFuture {
+        let promise = match call_device_method(&device.0, "popErrorScope", &[]) {
+            Some(Ok(promise)) => js_sys::Promise::from(promise),
+            Some(Err(error)) => js_sys::Promise::reject(&error),
+            None => js_sys::Promise::resolve(&wasm_bindgen::JsValue::NULL),
+        };
+        MakeSendFuture::new(
+            wasm_bindgen_futures::JsFuture::from(promise),
+            future_pop_error_scope,
+        )
+    }
+
     fn buffer_map_async(
         &self,
         buffer: &Self::BufferId,
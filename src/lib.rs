@@ -11,14 +11,19 @@ pub mod util;
 mod macros;
 
 use std::{
+    any::Any,
     borrow::Cow,
+    collections::HashMap,
     error,
     fmt::{Debug, Display},
     future::Future,
     marker::PhantomData,
     num::{NonZeroU32, NonZeroU8},
     ops::{Bound, Range, RangeBounds},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     thread,
 };
 
@@ -181,6 +186,9 @@ trait Context: Debug + Send + Sized + Sync {
     type RequestDeviceFuture: Future<Output = Result<(Self::DeviceId, Self::QueueId), RequestDeviceError>>
         + Send;
     type MapAsyncFuture: Future<Output = Result<(), BufferAsyncError>> + Send;
+    type PopErrorScopeFuture: Future<Output = Option<Error>> + Send;
+    type CreateRenderPipelineFuture: Future<Output = Result<Self::RenderPipelineId, Error>> + Send;
+    type CreateComputePipelineFuture: Future<Output = Result<Self::ComputePipelineId, Error>> + Send;
 
     fn init(backends: BackendBit) -> Self;
     fn instance_create_surface(
@@ -250,6 +258,16 @@ trait Context: Debug + Send + Sized + Sync {
         device: &Self::DeviceId,
         desc: &ComputePipelineDescriptor,
     ) -> Self::ComputePipelineId;
+    fn device_create_render_pipeline_async(
+        self: &Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture;
+    fn device_create_compute_pipeline_async(
+        self: &Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture;
     fn device_create_buffer(
         &self,
         device: &Self::DeviceId,
@@ -287,6 +305,8 @@ trait Context: Debug + Send + Sized + Sync {
         device: &Self::DeviceId,
         handler: impl UncapturedErrorHandler,
     );
+    fn device_push_error_scope(&self, device: &Self::DeviceId, filter: ErrorFilter);
+    fn device_pop_error_scope(&self, device: &Self::DeviceId) -> Self::PopErrorScopeFuture;
 
     fn buffer_map_async(
         &self,
@@ -486,6 +506,9 @@ impl Drop for Adapter {
 pub struct Device {
     context: Arc<C>,
     id: <C as Context>::DeviceId,
+    /// Number of error scopes pushed and not popped yet. Browsers don't report mismatched pops,
+    /// so they are detected here for all the backends.
+    error_scope_depth: AtomicUsize,
 }
 
 /// Passed to [`Device::poll`] to control if it should block or not. This has no effect on
@@ -722,6 +745,24 @@ pub enum ShaderSource<'a> {
     ///
     /// Note: WGSL is not yet supported on the Web.
     Wgsl(Cow<'a, str>),
+    /// GLSL module as a string slice, translated by naga's GLSL frontend.
+    ///
+    /// The entry point must be called `main`. On the Web, the module is converted to SPIR-V.
+    ///
+    /// Invalid stages and parsing errors are reported as validation errors, and the module is
+    /// created empty, so that the pipelines using it fail.
+    Glsl {
+        /// The GLSL source code.
+        code: Cow<'a, str>,
+        /// Stage of the `main` entry point, which must be a single stage.
+        stage: ShaderStage,
+        /// Preprocessor definitions, as pairs of name and value.
+        defines: HashMap<String, String>,
+    },
+    /// Module already in naga's intermediate representation.
+    ///
+    /// On the Web, the module is converted to SPIR-V.
+    Naga(naga::Module),
 }
 
 /// Descriptor for a shader module.
@@ -834,6 +875,13 @@ impl Drop for CommandBuffer {
     }
 }
 
+/// Marker of the types recording commands, which are !Sync. On native backends, they are
+/// Send, and on the web they are also !Send, as they are tied to the JavaScript thread.
+#[cfg(not(target_arch = "wasm32"))]
+type RecordingMarker = PhantomData<std::cell::Cell<()>>;
+#[cfg(target_arch = "wasm32")]
+type RecordingMarker = PhantomData<*const u8>;
+
 /// Encodes a series of GPU operations.
 ///
 /// A command encoder can record [`RenderPass`]es, [`ComputePass`]es,
@@ -841,13 +889,16 @@ impl Drop for CommandBuffer {
 ///
 /// When finished recording, call [`CommandEncoder::finish`] to obtain a [`CommandBuffer`] which may
 /// be submitted for execution.
+///
+/// On native backends, command encoders and their passes are `Send`: several encoders can be
+/// recorded on different threads, and their command buffers submitted together in order.
+/// [`util::ParallelRecorder`] does this for a list of draws.
 #[derive(Debug)]
 pub struct CommandEncoder {
     context: Arc<C>,
     id: <C as Context>::CommandEncoderId,
-    /// This type should be !Send !Sync, because it represents an allocation on this thread's
-    /// command buffer.
-    _p: PhantomData<*const u8>,
+    /// This type should be !Sync, because it can only be recorded by one thread at a time.
+    _p: RecordingMarker,
 }
 
 /// In-progress recording of a render pass.
@@ -857,6 +908,45 @@ pub struct RenderPass<'a> {
     parent: &'a mut CommandEncoder,
 }
 
+/// Maximum number of work groups in each dimension of a single dispatch.
+const MAX_DISPATCH_WORKGROUPS: u32 = 65535;
+
+/// Split a dispatch covering `total` invocations with work groups of `workgroup_size` into
+/// dispatches fitting [`MAX_DISPATCH_WORKGROUPS`].
+///
+/// Returns the ID of the first invocation and the work group counts of each dispatch.
+fn split_dispatch(total: [u32; 3], workgroup_size: [u32; 3]) -> Vec<([u32; 3], [u32; 3])> {
+    assert!(
+        workgroup_size.iter().all(|&size| size > 0),
+        "Work group sizes must not be 0"
+    );
+    let group_count = |dim: usize| {
+        total[dim] / workgroup_size[dim] + (total[dim] % workgroup_size[dim] != 0) as u32
+    };
+    let groups = [group_count(0), group_count(1), group_count(2)];
+
+    let step = MAX_DISPATCH_WORKGROUPS as usize;
+    let mut dispatches = Vec::new();
+    for z in (0..groups[2]).step_by(step) {
+        for y in (0..groups[1]).step_by(step) {
+            for x in (0..groups[0]).step_by(step) {
+                let base_id = [
+                    x * workgroup_size[0],
+                    y * workgroup_size[1],
+                    z * workgroup_size[2],
+                ];
+                let counts = [
+                    (groups[0] - x).min(MAX_DISPATCH_WORKGROUPS),
+                    (groups[1] - y).min(MAX_DISPATCH_WORKGROUPS),
+                    (groups[2] - z).min(MAX_DISPATCH_WORKGROUPS),
+                ];
+                dispatches.push((base_id, counts));
+            }
+        }
+    }
+    dispatches
+}
+
 /// In-progress recording of a compute pass.
 #[derive(Debug)]
 pub struct ComputePass<'a> {
@@ -864,20 +954,47 @@ pub struct ComputePass<'a> {
     parent: &'a mut CommandEncoder,
 }
 
+/// In-progress recording of a render pass which keeps the resources it uses alive.
+///
+/// Unlike [`RenderPass`], it takes shared ownership of the buffers, bind groups, pipelines and
+/// render bundles it is given, so they don't need to outlive the pass. They are released
+/// when the pass ends.
+#[derive(Debug)]
+pub struct OwnedRenderPass<'a> {
+    // Declared first so that the pass ends before the resources are released
+    pass: RenderPass<'a>,
+    resources: Vec<Arc<dyn Any + Send + Sync>>,
+}
+
+/// In-progress recording of a compute pass which keeps the resources it uses alive.
+///
+/// Unlike [`ComputePass`], it takes shared ownership of the buffers, bind groups and pipelines
+/// it is given, so they don't need to outlive the pass. They are released when the pass ends.
+#[derive(Debug)]
+pub struct OwnedComputePass<'a> {
+    // Declared first so that the pass ends before the resources are released
+    pass: ComputePass<'a>,
+    resources: Vec<Arc<dyn Any + Send + Sync>>,
+}
+
 /// Encodes a series of GPU operations into a reusable "render bundle".
 ///
 /// It only supports a handful of render commands, but it makes them reusable. [`RenderBundle`]s
 /// can be executed onto a [`CommandEncoder`] using [`RenderPass::execute_bundles`].
 ///
 /// Executing a [`RenderBundle`] is often more efficient then issuing the underlying commands manually.
+///
+/// On native backends, render bundle encoders are `Send`, so that bundles can be recorded on
+/// worker threads, as done by [`util::ParallelRecorder`].
 #[derive(Debug)]
 pub struct RenderBundleEncoder<'a> {
     context: Arc<C>,
     id: <C as Context>::RenderBundleEncoderId,
     _parent: &'a Device,
-    /// This type should be !Send !Sync, because it represents an allocation on this thread's
-    /// command buffer.
-    _p: PhantomData<*const u8>,
+    /// Features of the parent device, checked by the commands which require them.
+    features: Features,
+    /// This type should be !Sync, because it can only be recorded by one thread at a time.
+    _p: RecordingMarker,
 }
 
 /// Pre-prepared reusable bundle of GPU operations.
@@ -925,6 +1042,9 @@ pub struct Queue {
 }
 
 /// Resource that can be bound to a pipeline.
+///
+/// Only texture views can be bound as arrays: the pinned wgpu-core has no binding resource for
+/// arrays of buffers or samplers, so layout entries with a `count` must be textures.
 #[non_exhaustive]
 #[derive(Clone, Debug)]
 pub enum BindingResource<'a> {
@@ -1186,10 +1306,38 @@ pub struct VertexState<'a> {
     /// The name of the entry point in the compiled shader. There must be a function that returns
     /// void with this name in the shader.
     pub entry_point: &'a str,
+    /// Values of the pipeline-overridable constants of the shader, by name or numeric ID.
+    ///
+    /// On native, they specialize the constants of a SPIR-V module.
+    pub constants: &'a [(PipelineConstantId<'a>, f64)],
     /// The format of any vertex buffers used with this pipeline.
     pub buffers: &'a [VertexBufferLayout<'a>],
 }
 
+/// Identifies a pipeline-overridable constant of a shader.
+///
+/// With SPIR-V, these are specialization constants: a name matches the `OpName`
+/// of the constant, and an ID its `SpecId` decoration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PipelineConstantId<'a> {
+    /// Constant identified by its name in the shader.
+    Name(&'a str),
+    /// Constant identified by its numeric ID.
+    Id(u32),
+}
+
+impl<'a> From<&'a str> for PipelineConstantId<'a> {
+    fn from(name: &'a str) -> Self {
+        PipelineConstantId::Name(name)
+    }
+}
+
+impl From<u32> for PipelineConstantId<'_> {
+    fn from(id: u32) -> Self {
+        PipelineConstantId::Id(id)
+    }
+}
+
 /// Describes the fragment process in a render pipeline.
 #[derive(Clone, Debug)]
 pub struct FragmentState<'a> {
@@ -1198,6 +1346,10 @@ pub struct FragmentState<'a> {
     /// The name of the entry point in the compiled shader. There must be a function that returns
     /// void with this name in the shader.
     pub entry_point: &'a str,
+    /// Values of the pipeline-overridable constants of the shader, by name or numeric ID.
+    ///
+    /// On native, they specialize the constants of a SPIR-V module.
+    pub constants: &'a [(PipelineConstantId<'a>, f64)],
     /// The color state of the render targets.
     pub targets: &'a [ColorTargetState],
 }
@@ -1240,6 +1392,10 @@ pub struct ComputePipelineDescriptor<'a> {
     /// The name of the entry point in the compiled shader. There must be a function that returns
     /// void with this name in the shader.
     pub entry_point: &'a str,
+    /// Values of the pipeline-overridable constants of the shader, by name or numeric ID.
+    ///
+    /// On native, they specialize the constants of a SPIR-V module.
+    pub constants: &'a [(PipelineConstantId<'a>, f64)],
 }
 
 pub use wgt::BufferCopyView as BufferCopyViewBase;
@@ -1430,6 +1586,7 @@ impl Adapter {
                     Device {
                         context: Arc::clone(&context),
                         id: device_id,
+                        error_scope_depth: AtomicUsize::new(0),
                     },
                     Queue {
                         context,
@@ -1526,12 +1683,21 @@ impl Device {
             context: Arc::clone(&self.context),
             id: Context::device_create_render_bundle_encoder(&*self.context, &self.id, desc),
             _parent: self,
+            features: self.features(),
             _p: Default::default(),
         }
     }
 
     /// Creates a new [`BindGroup`].
+    ///
+    /// Without a label, the bind group is labelled after the calling code.
+    #[track_caller]
     pub fn create_bind_group(&self, desc: &BindGroupDescriptor) -> BindGroup {
+        let caller = caller_label(desc.label);
+        let desc = &BindGroupDescriptor {
+            label: desc.label.or_else(|| caller.as_deref()),
+            ..*desc
+        };
         BindGroup {
             context: Arc::clone(&self.context),
             id: Context::device_create_bind_group(&*self.context, &self.id, desc),
@@ -1570,8 +1736,39 @@ impl Device {
         }
     }
 
+    /// Creates a [`RenderPipeline`] without blocking on the shader compilation.
+    ///
+    /// On native, the pipeline is compiled on a background thread. On the web, this maps to
+    /// `createRenderPipelineAsync`. Errors are returned by the future instead of being
+    /// reported to the error scopes or the uncaptured error handler.
+    pub fn create_render_pipeline_async(
+        &self,
+        desc: &RenderPipelineDescriptor,
+    ) -> impl Future<Output = Result<RenderPipeline, Error>> + Send {
+        let context = Arc::clone(&self.context);
+        let future = Context::device_create_render_pipeline_async(&context, &self.id, desc);
+        async move { future.await.map(|id| RenderPipeline { context, id }) }
+    }
+
+    /// Creates a [`ComputePipeline`] without blocking on the shader compilation.
+    ///
+    /// See [`Device::create_render_pipeline_async`] for details.
+    pub fn create_compute_pipeline_async(
+        &self,
+        desc: &ComputePipelineDescriptor,
+    ) -> impl Future<Output = Result<ComputePipeline, Error>> + Send {
+        let context = Arc::clone(&self.context);
+        let future = Context::device_create_compute_pipeline_async(&context, &self.id, desc);
+        async move { future.await.map(|id| ComputePipeline { context, id }) }
+    }
+
     /// Creates a [`Buffer`].
+    ///
+    /// Without a label, the buffer is labelled after the calling code.
+    #[track_caller]
     pub fn create_buffer(&self, desc: &BufferDescriptor) -> Buffer {
+        let caller = caller_label(desc.label);
+        let desc = &desc.map_label(|label| label.or_else(|| caller.as_deref()));
         let mut map_context = MapContext::new(desc.size);
         if desc.mapped_at_creation {
             map_context.initial_range = 0..desc.size;
@@ -1586,8 +1783,12 @@ impl Device {
 
     /// Creates a new [`Texture`].
     ///
-    /// `desc` specifies the general format of the texture.
+    /// `desc` specifies the general format of the texture. Without a label, the texture is
+    /// labelled after the calling code.
+    #[track_caller]
     pub fn create_texture(&self, desc: &TextureDescriptor) -> Texture {
+        let caller = caller_label(desc.label);
+        let desc = &desc.map_label(|label| label.or_else(|| caller.as_deref()));
         Texture {
             context: Arc::clone(&self.context),
             id: Context::device_create_texture(&*self.context, &self.id, desc),
@@ -1597,8 +1798,15 @@ impl Device {
 
     /// Creates a new [`Sampler`].
     ///
-    /// `desc` specifies the behavior of the sampler.
+    /// `desc` specifies the behavior of the sampler. Without a label, the sampler is labelled
+    /// after the calling code.
+    #[track_caller]
     pub fn create_sampler(&self, desc: &SamplerDescriptor) -> Sampler {
+        let caller = caller_label(desc.label);
+        let desc = &SamplerDescriptor {
+            label: desc.label.or_else(|| caller.as_deref()),
+            ..*desc
+        };
         Sampler {
             context: Arc::clone(&self.context),
             id: Context::device_create_sampler(&*self.context, &self.id, desc),
@@ -1630,6 +1838,36 @@ impl Device {
     pub fn on_uncaptured_error(&self, handler: impl UncapturedErrorHandler) {
         self.context.device_on_uncaptured_error(&self.id, handler);
     }
+
+    /// Push an error scope.
+    ///
+    /// Until the matching [`Device::pop_error_scope`], the first error of the kind selected
+    /// by `filter` is captured by the scope instead of reaching the uncaptured error handler.
+    ///
+    /// On the web, browsers which don't implement error scopes ignore them, and popping the
+    /// scope resolves to `None`.
+    pub fn push_error_scope(&self, filter: ErrorFilter) {
+        self.error_scope_depth.fetch_add(1, Ordering::SeqCst);
+        self.context.device_push_error_scope(&self.id, filter);
+    }
+
+    /// Pop the innermost error scope, resolving to the error it captured, if any.
+    ///
+    /// # Panics
+    ///
+    /// - There is no error scope left to pop.
+    pub fn pop_error_scope(&self) -> impl Future<Output = Option<Error>> + Send {
+        let popped =
+            self.error_scope_depth
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |depth| {
+                    depth.checked_sub(1)
+                });
+        assert!(
+            popped.is_ok(),
+            "Mismatched pop_error_scope call: no error scope for this device"
+        );
+        self.context.device_pop_error_scope(&self.id)
+    }
 }
 
 impl Drop for Device {
@@ -1877,7 +2115,15 @@ impl Drop for Buffer {
 
 impl Texture {
     /// Creates a view of this texture.
+    ///
+    /// Without a label, the view is labelled after the calling code.
+    #[track_caller]
     pub fn create_view(&self, desc: &TextureViewDescriptor) -> TextureView {
+        let caller = caller_label(desc.label);
+        let desc = &TextureViewDescriptor {
+            label: desc.label.or_else(|| caller.as_deref()),
+            ..*desc
+        };
         TextureView {
             context: Arc::clone(&self.context),
             id: Context::texture_create_view(&*self.context, &self.id, desc),
@@ -1939,6 +2185,29 @@ impl CommandEncoder {
         }
     }
 
+    /// Begins recording of a render pass which keeps the resources it uses alive.
+    ///
+    /// This function returns an [`OwnedRenderPass`] object which records a single render pass.
+    pub fn begin_render_pass_owned<'a>(
+        &'a mut self,
+        desc: &RenderPassDescriptor<'a, '_>,
+    ) -> OwnedRenderPass<'a> {
+        OwnedRenderPass {
+            pass: self.begin_render_pass(desc),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Begins recording of a compute pass which keeps the resources it uses alive.
+    ///
+    /// This function returns an [`OwnedComputePass`] object which records a single compute pass.
+    pub fn begin_compute_pass_owned(&mut self, desc: &ComputePassDescriptor) -> OwnedComputePass {
+        OwnedComputePass {
+            pass: self.begin_compute_pass(desc),
+            resources: Vec::new(),
+        }
+    }
+
     /// Copy data from one buffer to another.
     ///
     /// # Panics
@@ -2044,6 +2313,12 @@ impl CommandEncoder {
     pub fn pop_debug_group(&mut self) {
         Context::command_encoder_pop_debug_group(&*self.context, &self.id);
     }
+
+    /// Start recording commands into a debug group, which ends when the returned guard is
+    /// dropped. Commands are recorded through the guard.
+    pub fn debug_group(&mut self, label: &str) -> DebugGroup<'_, Self> {
+        DebugGroup::new(self, label)
+    }
 }
 
 /// [`Features::TIMESTAMP_QUERY`] must be enabled on the device in order to call these functions.
@@ -2195,6 +2470,12 @@ impl<'a> RenderPass<'a> {
         self.id.pop_debug_group();
     }
 
+    /// Start recording commands into a debug group, which ends when the returned guard is
+    /// dropped. Commands are recorded through the guard.
+    pub fn debug_group(&mut self, label: &str) -> DebugGroup<'_, Self> {
+        DebugGroup::new(self, label)
+    }
+
     /// Draws indexed primitives using the active index buffer and the active vertex buffers.
     ///
     /// The active index buffer can be set with [`RenderPass::set_index_buffer`], while the active
@@ -2527,6 +2808,12 @@ impl<'a> ComputePass<'a> {
         self.id.pop_debug_group();
     }
 
+    /// Start recording commands into a debug group, which ends when the returned guard is
+    /// dropped. Commands are recorded through the guard.
+    pub fn debug_group(&mut self, label: &str) -> DebugGroup<'_, Self> {
+        DebugGroup::new(self, label)
+    }
+
     /// Dispatches compute work operations.
     ///
     /// `x`, `y` and `z` denote the number of work groups to dispatch in each dimension.
@@ -2556,6 +2843,41 @@ impl<'a> ComputePass<'a> {
     pub fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
         self.id.set_push_constants(offset, data);
     }
+
+    /// Dispatches enough work groups of `workgroup_size` invocations to cover `total`
+    /// invocations in each dimension.
+    ///
+    /// Dispatches of more than 65535 work groups in a dimension are split into several
+    /// dispatches. Before each dispatch, including when there is a single one, the ID of its
+    /// first invocation is written to the push constants as a `[u32; 3]` at offset 0, to be
+    /// added to the global invocation ID:
+    ///
+    /// ```glsl
+    /// layout(push_constant) uniform Dispatch { uvec3 base_id; };
+    ///
+    /// void main() {
+    ///     uvec3 id = base_id + gl_GlobalInvocationID;
+    ///     // ...
+    /// }
+    /// ```
+    ///
+    /// The pipeline layout must have a push constant range visible to the compute stage which
+    /// covers bytes `0..12`. Push constants aren't available on the web. The last work groups
+    /// may extend past `total`, so the shader has to check its bounds.
+    ///
+    /// # Panics
+    ///
+    /// - A dimension of `workgroup_size` is 0.
+    pub fn dispatch_for_size(&mut self, total: [u32; 3], workgroup_size: [u32; 3]) {
+        for (base_id, groups) in split_dispatch(total, workgroup_size) {
+            let mut data = [0u8; 12];
+            for (bytes, value) in data.chunks_exact_mut(4).zip(base_id.iter()) {
+                bytes.copy_from_slice(&value.to_ne_bytes());
+            }
+            self.set_push_constants(0, &data);
+            self.dispatch(groups[0], groups[1], groups[2]);
+        }
+    }
 }
 
 /// [`Features::TIMESTAMP_QUERY`] must be enabled on the device in order to call these functions.
@@ -2597,6 +2919,479 @@ impl<'a> Drop for ComputePass<'a> {
     }
 }
 
+impl<'a> OwnedRenderPass<'a> {
+    /// Sets the active bind group for a given bind group index.
+    ///
+    /// See [`RenderPass::set_bind_group`].
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: Arc<BindGroup>,
+        offsets: &[DynamicOffset],
+    ) {
+        RenderInner::set_bind_group(&mut self.pass.id, index, &bind_group.id, offsets);
+        self.resources.push(bind_group);
+    }
+
+    /// Sets the active render pipeline.
+    ///
+    /// Subsequent draw calls will exhibit the behavior defined by `pipeline`.
+    pub fn set_pipeline(&mut self, pipeline: Arc<RenderPipeline>) {
+        RenderInner::set_pipeline(&mut self.pass.id, &pipeline.id);
+        self.resources.push(pipeline);
+    }
+
+    /// Sets the blend color as used by some of the blending modes.
+    ///
+    /// Subsequent blending tests will test against this value.
+    pub fn set_blend_color(&mut self, color: Color) {
+        self.pass.set_blend_color(color);
+    }
+
+    /// Sets the active index buffer to the `bounds` range of `buffer`.
+    ///
+    /// See [`RenderPass::set_index_buffer`].
+    pub fn set_index_buffer<S: RangeBounds<BufferAddress>>(
+        &mut self,
+        buffer: Arc<Buffer>,
+        bounds: S,
+        index_format: IndexFormat,
+    ) {
+        let (offset, size) = range_to_offset_size(bounds);
+        RenderInner::set_index_buffer(&mut self.pass.id, &buffer.id, index_format, offset, size);
+        self.resources.push(buffer);
+    }
+
+    /// Assign the `bounds` range of `buffer` to a vertex buffer slot.
+    ///
+    /// See [`RenderPass::set_vertex_buffer`].
+    pub fn set_vertex_buffer<S: RangeBounds<BufferAddress>>(
+        &mut self,
+        slot: u32,
+        buffer: Arc<Buffer>,
+        bounds: S,
+    ) {
+        let (offset, size) = range_to_offset_size(bounds);
+        RenderInner::set_vertex_buffer(&mut self.pass.id, slot, &buffer.id, offset, size);
+        self.resources.push(buffer);
+    }
+
+    /// Sets the scissor region.
+    ///
+    /// Subsequent draw calls will discard any fragments that fall outside this region.
+    pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.pass.set_scissor_rect(x, y, width, height);
+    }
+
+    /// Sets the viewport region.
+    ///
+    /// Subsequent draw calls will draw any fragments in this region.
+    pub fn set_viewport(&mut self, x: f32, y: f32, w: f32, h: f32, min_depth: f32, max_depth: f32) {
+        self.pass.set_viewport(x, y, w, h, min_depth, max_depth);
+    }
+
+    /// Sets the stencil reference.
+    ///
+    /// Subsequent stencil tests will test against this value.
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        self.pass.set_stencil_reference(reference);
+    }
+
+    /// Draws primitives from the active vertex buffer(s).
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.pass.draw(vertices, instances);
+    }
+
+    /// Draws indexed primitives using the active index buffer and the active vertex buffers.
+    pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.pass.draw_indexed(indices, base_vertex, instances);
+    }
+
+    /// Draws primitives from the active vertex buffer(s) based on the contents of the `indirect_buffer`.
+    ///
+    /// See [`RenderPass::draw_indirect`].
+    pub fn draw_indirect(&mut self, indirect_buffer: Arc<Buffer>, indirect_offset: BufferAddress) {
+        self.pass
+            .id
+            .draw_indirect(&indirect_buffer.id, indirect_offset);
+        self.resources.push(indirect_buffer);
+    }
+
+    /// Draws indexed primitives using the active index buffer and the active vertex buffers,
+    /// based on the contents of the `indirect_buffer`.
+    ///
+    /// See [`RenderPass::draw_indexed_indirect`].
+    pub fn draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: Arc<Buffer>,
+        indirect_offset: BufferAddress,
+    ) {
+        self.pass
+            .id
+            .draw_indexed_indirect(&indirect_buffer.id, indirect_offset);
+        self.resources.push(indirect_buffer);
+    }
+
+    /// Execute [render bundles][RenderBundle], which are sets of pre-recorded commands
+    /// that can be run together.
+    pub fn execute_bundles<I: IntoIterator<Item = Arc<RenderBundle>>>(
+        &mut self,
+        render_bundles: I,
+    ) {
+        let render_bundles = render_bundles.into_iter().collect::<Vec<_>>();
+        self.pass
+            .id
+            .execute_bundles(render_bundles.iter().map(|rb| &rb.id));
+        self.resources.extend(
+            render_bundles
+                .into_iter()
+                .map(|rb| rb as Arc<dyn Any + Send + Sync>),
+        );
+    }
+
+    /// Inserts debug marker.
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        self.pass.insert_debug_marker(label);
+    }
+
+    /// Start record commands and group it into debug marker group.
+    pub fn push_debug_group(&mut self, label: &str) {
+        self.pass.push_debug_group(label);
+    }
+
+    /// Stops command recording and creates debug group.
+    pub fn pop_debug_group(&mut self) {
+        self.pass.pop_debug_group();
+    }
+
+    /// Start recording commands into a debug group, which ends when the returned guard is
+    /// dropped. Commands are recorded through the guard.
+    pub fn debug_group(&mut self, label: &str) -> DebugGroup<'_, Self> {
+        DebugGroup::new(self, label)
+    }
+}
+
+/// [`Features::MULTI_DRAW_INDIRECT`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedRenderPass<'a> {
+    /// Disptaches `count` draw calls from the active vertex buffer(s) based on the contents of
+    /// the `indirect_buffer`.
+    ///
+    /// See [`RenderPass::multi_draw_indirect`].
+    pub fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: Arc<Buffer>,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        self.pass
+            .id
+            .multi_draw_indirect(&indirect_buffer.id, indirect_offset, count);
+        self.resources.push(indirect_buffer);
+    }
+
+    /// Disptaches `count` draw calls from the active index buffer and the active vertex buffers,
+    /// based on the contents of the `indirect_buffer`.
+    ///
+    /// See [`RenderPass::multi_draw_indexed_indirect`].
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: Arc<Buffer>,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        self.pass
+            .id
+            .multi_draw_indexed_indirect(&indirect_buffer.id, indirect_offset, count);
+        self.resources.push(indirect_buffer);
+    }
+}
+
+/// [`Features::MULTI_DRAW_INDIRECT_COUNT`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedRenderPass<'a> {
+    /// Disptaches multiple draw calls from the active vertex buffer(s) based on the contents of
+    /// the `indirect_buffer`, reading the number of draws from `count_buffer`.
+    ///
+    /// See [`RenderPass::multi_draw_indirect_count`].
+    pub fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: Arc<Buffer>,
+        indirect_offset: BufferAddress,
+        count_buffer: Arc<Buffer>,
+        count_offset: BufferAddress,
+        max_count: u32,
+    ) {
+        self.pass.id.multi_draw_indirect_count(
+            &indirect_buffer.id,
+            indirect_offset,
+            &count_buffer.id,
+            count_offset,
+            max_count,
+        );
+        self.resources.push(indirect_buffer);
+        self.resources.push(count_buffer);
+    }
+
+    /// Disptaches multiple draw calls from the active index buffer and the active vertex buffers,
+    /// based on the contents of the `indirect_buffer`, reading the number of draws from
+    /// `count_buffer`.
+    ///
+    /// See [`RenderPass::multi_draw_indexed_indirect_count`].
+    pub fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: Arc<Buffer>,
+        indirect_offset: BufferAddress,
+        count_buffer: Arc<Buffer>,
+        count_offset: BufferAddress,
+        max_count: u32,
+    ) {
+        self.pass.id.multi_draw_indexed_indirect_count(
+            &indirect_buffer.id,
+            indirect_offset,
+            &count_buffer.id,
+            count_offset,
+            max_count,
+        );
+        self.resources.push(indirect_buffer);
+        self.resources.push(count_buffer);
+    }
+}
+
+/// [`Features::PUSH_CONSTANTS`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedRenderPass<'a> {
+    /// Set push constant data.
+    ///
+    /// See [`RenderPass::set_push_constants`].
+    pub fn set_push_constants(&mut self, stages: wgt::ShaderStage, offset: u32, data: &[u8]) {
+        self.pass.set_push_constants(stages, offset, data);
+    }
+}
+
+/// [`Features::TIMESTAMP_QUERY`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedRenderPass<'a> {
+    /// Issue a timestamp command at this point in the queue.
+    ///
+    /// See [`RenderPass::write_timestamp`].
+    pub fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32) {
+        self.pass.write_timestamp(query_set, query_index);
+    }
+}
+
+/// [`Features::PIPEILNE_STATISTICS_QUERY`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedRenderPass<'a> {
+    /// Start a pipeline statistics query on this render pass. It can be ended with
+    /// `end_pipeline_statistics_query`. Pipeline statistics queries may not be nested.
+    pub fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        self.pass
+            .begin_pipeline_statistics_query(query_set, query_index);
+    }
+
+    /// End the pipeline statistics query on this render pass. It can be started with
+    /// `begin_pipeline_statistics_query`. Pipeline statistics queries may not be nested.
+    pub fn end_pipeline_statistics_query(&mut self) {
+        self.pass.end_pipeline_statistics_query();
+    }
+}
+
+impl<'a> OwnedComputePass<'a> {
+    /// Sets the active bind group for a given bind group index.
+    ///
+    /// See [`ComputePass::set_bind_group`].
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: Arc<BindGroup>,
+        offsets: &[DynamicOffset],
+    ) {
+        ComputePassInner::set_bind_group(&mut self.pass.id, index, &bind_group.id, offsets);
+        self.resources.push(bind_group);
+    }
+
+    /// Sets the active compute pipeline.
+    pub fn set_pipeline(&mut self, pipeline: Arc<ComputePipeline>) {
+        ComputePassInner::set_pipeline(&mut self.pass.id, &pipeline.id);
+        self.resources.push(pipeline);
+    }
+
+    /// Inserts debug marker.
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        self.pass.insert_debug_marker(label);
+    }
+
+    /// Start record commands and group it into debug marker group.
+    pub fn push_debug_group(&mut self, label: &str) {
+        self.pass.push_debug_group(label);
+    }
+
+    /// Stops command recording and creates debug group.
+    pub fn pop_debug_group(&mut self) {
+        self.pass.pop_debug_group();
+    }
+
+    /// Start recording commands into a debug group, which ends when the returned guard is
+    /// dropped. Commands are recorded through the guard.
+    pub fn debug_group(&mut self, label: &str) -> DebugGroup<'_, Self> {
+        DebugGroup::new(self, label)
+    }
+
+    /// Dispatches compute work operations.
+    ///
+    /// `x`, `y` and `z` denote the number of work groups to dispatch in each dimension.
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        self.pass.dispatch(x, y, z);
+    }
+
+    /// Dispatches compute work operations, based on the contents of the `indirect_buffer`.
+    pub fn dispatch_indirect(
+        &mut self,
+        indirect_buffer: Arc<Buffer>,
+        indirect_offset: BufferAddress,
+    ) {
+        ComputePassInner::dispatch_indirect(
+            &mut self.pass.id,
+            &indirect_buffer.id,
+            indirect_offset,
+        );
+        self.resources.push(indirect_buffer);
+    }
+}
+
+/// [`Features::PUSH_CONSTANTS`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedComputePass<'a> {
+    /// Set push constant data.
+    ///
+    /// See [`ComputePass::set_push_constants`].
+    pub fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
+        self.pass.set_push_constants(offset, data);
+    }
+
+    /// Dispatches enough work groups of `workgroup_size` invocations to cover `total`
+    /// invocations in each dimension.
+    ///
+    /// See [`ComputePass::dispatch_for_size`].
+    pub fn dispatch_for_size(&mut self, total: [u32; 3], workgroup_size: [u32; 3]) {
+        self.pass.dispatch_for_size(total, workgroup_size);
+    }
+}
+
+/// [`Features::TIMESTAMP_QUERY`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedComputePass<'a> {
+    /// Issue a timestamp command at this point in the queue.
+    ///
+    /// See [`ComputePass::write_timestamp`].
+    pub fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32) {
+        self.pass.write_timestamp(query_set, query_index);
+    }
+}
+
+/// [`Features::PIPEILNE_STATISTICS_QUERY`] must be enabled on the device in order to call these functions.
+impl<'a> OwnedComputePass<'a> {
+    /// Start a pipeline statistics query on this compute pass. It can be ended with
+    /// `end_pipeline_statistics_query`. Pipeline statistics queries may not be nested.
+    pub fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        self.pass
+            .begin_pipeline_statistics_query(query_set, query_index);
+    }
+
+    /// End the pipeline statistics query on this compute pass. It can be started with
+    /// `begin_pipeline_statistics_query`. Pipeline statistics queries may not be nested.
+    pub fn end_pipeline_statistics_query(&mut self) {
+        self.pass.end_pipeline_statistics_query();
+    }
+}
+
+/// Encoder whose commands can be grouped into nested debug groups.
+///
+/// It is implemented by [`CommandEncoder`] and by the render and compute passes, to be used
+/// with [`DebugGroup`].
+pub trait DebugGroupEncoder {
+    /// Start recording commands into a debug group.
+    fn push_debug_group(&mut self, label: &str);
+    /// End the innermost debug group.
+    fn pop_debug_group(&mut self);
+}
+
+/// Debug group which ends when dropped, so that debug groups are always balanced.
+///
+/// Returned by the `debug_group` methods of [`CommandEncoder`] and of the passes. Commands are
+/// recorded into the group through the guard, which dereferences to the encoder.
+///
+/// ```no_run
+/// # fn example(encoder: &mut wgpu::CommandEncoder) {
+/// let mut shadow = encoder.debug_group("shadow");
+/// for _cascade in 0..4 {
+///     let mut cascade = shadow.debug_group("cascade");
+///     // record the cascade with `cascade.begin_render_pass`
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DebugGroup<'a, E: DebugGroupEncoder> {
+    encoder: &'a mut E,
+}
+
+impl<'a, E: DebugGroupEncoder> DebugGroup<'a, E> {
+    fn new(encoder: &'a mut E, label: &str) -> Self {
+        encoder.push_debug_group(label);
+        Self { encoder }
+    }
+}
+
+impl<E: DebugGroupEncoder> std::ops::Deref for DebugGroup<'_, E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        self.encoder
+    }
+}
+
+impl<E: DebugGroupEncoder> std::ops::DerefMut for DebugGroup<'_, E> {
+    fn deref_mut(&mut self) -> &mut E {
+        self.encoder
+    }
+}
+
+impl<E: DebugGroupEncoder> Drop for DebugGroup<'_, E> {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.encoder.pop_debug_group();
+        }
+    }
+}
+
+macro_rules! impl_debug_group_encoder {
+    ($($ty:ty),*) => {$(
+        impl DebugGroupEncoder for $ty {
+            fn push_debug_group(&mut self, label: &str) {
+                <$ty>::push_debug_group(self, label);
+            }
+
+            fn pop_debug_group(&mut self) {
+                <$ty>::pop_debug_group(self);
+            }
+        }
+    )*};
+}
+
+impl_debug_group_encoder!(
+    CommandEncoder,
+    RenderPass<'_>,
+    ComputePass<'_>,
+    OwnedRenderPass<'_>,
+    OwnedComputePass<'_>
+);
+
+/// Label of the code creating a resource, used when its descriptor has no label.
+#[track_caller]
+fn caller_label(label: Label) -> Option<String> {
+    match label {
+        Some(_) => None,
+        None => {
+            let location = std::panic::Location::caller();
+            Some(format!("{}:{}", location.file(), location.line()))
+        }
+    }
+}
+
 impl<'a> RenderBundleEncoder<'a> {
     /// Finishes recording and returns a [`RenderBundle`] that can be executed in other render passes.
     pub fn finish(self, desc: &RenderBundleDescriptor) -> RenderBundle {
@@ -2722,6 +3517,85 @@ impl<'a> RenderBundleEncoder<'a> {
     }
 }
 
+/// [`Features::MULTI_DRAW_INDIRECT`] must be enabled on the device in order to call these functions.
+impl<'a> RenderBundleEncoder<'a> {
+    /// Disptaches multiple draw calls from the active vertex buffer(s) based on the contents of the `indirect_buffer`.
+    /// `count` draw calls are issued.
+    ///
+    /// The active vertex buffers can be set with [`RenderBundleEncoder::set_vertex_buffer`].
+    ///
+    /// The structure expected in `indirect_buffer` is the following:
+    ///
+    /// ```rust
+    /// #[repr(C)]
+    /// struct DrawIndirect {
+    ///     vertex_count: u32, // The number of vertices to draw.
+    ///     instance_count: u32, // The number of instances to draw.
+    ///     base_vertex: u32, // The Index of the first vertex to draw.
+    ///     base_instance: u32, // The instance ID of the first instance to draw.
+    /// }
+    /// ```
+    ///
+    /// These draw structures are expected to be tightly packed.
+    ///
+    /// The variants reading the draw count from a buffer are not available in render bundles.
+    ///
+    /// # Panics
+    ///
+    /// - [`Features::MULTI_DRAW_INDIRECT`] is not enabled on the device.
+    pub fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        assert!(
+            self.features.contains(Features::MULTI_DRAW_INDIRECT),
+            "MULTI_DRAW_INDIRECT feature must be enabled to call multi_draw_indirect"
+        );
+        self.id
+            .multi_draw_indirect(&indirect_buffer.id, indirect_offset, count);
+    }
+
+    /// Disptaches multiple draw calls from the active index buffer and the active vertex buffers,
+    /// based on the contents of the `indirect_buffer`. `count` draw calls are issued.
+    ///
+    /// The active index buffer can be set with [`RenderBundleEncoder::set_index_buffer`], while the active
+    /// vertex buffers can be set with [`RenderBundleEncoder::set_vertex_buffer`].
+    ///
+    /// The structure expected in `indirect_buffer` is the following:
+    ///
+    /// ```rust
+    /// #[repr(C)]
+    /// struct DrawIndexedIndirect {
+    ///     vertex_count: u32, // The number of vertices to draw.
+    ///     instance_count: u32, // The number of instances to draw.
+    ///     base_index: u32, // The base index within the index buffer.
+    ///     vertex_offset: i32, // The value added to the vertex index before indexing into the vertex buffer.
+    ///     base_instance: u32, // The instance ID of the first instance to draw.
+    /// }
+    /// ```
+    ///
+    /// These draw structures are expected to be tightly packed.
+    ///
+    /// # Panics
+    ///
+    /// - [`Features::MULTI_DRAW_INDIRECT`] is not enabled on the device.
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        assert!(
+            self.features.contains(Features::MULTI_DRAW_INDIRECT),
+            "MULTI_DRAW_INDIRECT feature must be enabled to call multi_draw_indexed_indirect"
+        );
+        self.id
+            .multi_draw_indexed_indirect(&indirect_buffer.id, indirect_offset, count);
+    }
+}
+
 /// [`Features::PUSH_CONSTANTS`] must be enabled on the device in order to call these functions.
 impl<'a> RenderBundleEncoder<'a> {
     /// Set push constant data.
@@ -2845,6 +3719,15 @@ impl SwapChain {
     }
 }
 
+/// Kind of errors captured by an error scope.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ErrorFilter {
+    /// Catch only out-of-memory errors.
+    OutOfMemory,
+    /// Catch only validation errors.
+    Validation,
+}
+
 /// Type for the callback of uncaptured error handler
 pub trait UncapturedErrorHandler: Fn(Error) + Send + Sync + 'static {}
 impl<T> UncapturedErrorHandler for T where T: Fn(Error) + Send + Sync + 'static {}
@@ -2866,6 +3749,16 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Kind of error scope this error is captured by.
+    pub fn filter(&self) -> ErrorFilter {
+        match self {
+            Error::OutOfMemoryError { .. } => ErrorFilter::OutOfMemory,
+            Error::ValidationError { .. } => ErrorFilter::Validation,
+        }
+    }
+}
+
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
@@ -2883,3 +3776,86 @@ impl Display for Error {
         }
     }
 }
+
+#[cfg(test)]
+#[derive(Debug, Default)]
+struct DebugGroupRecorder(Vec<String>);
+
+#[cfg(test)]
+impl DebugGroupEncoder for DebugGroupRecorder {
+    fn push_debug_group(&mut self, label: &str) {
+        self.0.push(format!("push {}", label));
+    }
+
+    fn pop_debug_group(&mut self) {
+        self.0.push("pop".to_string());
+    }
+}
+
+#[test]
+fn debug_groups_are_balanced() {
+    let mut recorder = DebugGroupRecorder::default();
+    {
+        let mut frame = DebugGroup::new(&mut recorder, "frame");
+        for index in 0..2 {
+            let mut pass = DebugGroup::new(&mut *frame, &format!("pass {}", index));
+            pass.0.push("draw".to_string());
+        }
+    }
+    assert_eq!(
+        recorder.0,
+        [
+            "push frame",
+            "push pass 0",
+            "draw",
+            "pop",
+            "push pass 1",
+            "draw",
+            "pop",
+            "pop"
+        ]
+    );
+
+    // Groups are also popped when leaving early
+    fn record(recorder: &mut DebugGroupRecorder) -> Result<(), ()> {
+        let mut outer = DebugGroup::new(recorder, "outer");
+        let _inner = DebugGroup::new(&mut *outer, "inner");
+        Err(())
+    }
+    let mut recorder = DebugGroupRecorder::default();
+    assert_eq!(record(&mut recorder), Err(()));
+    assert_eq!(recorder.0, ["push outer", "push inner", "pop", "pop"]);
+}
+
+#[test]
+fn dispatch_splitting() {
+    // Partial work groups are rounded up
+    assert_eq!(
+        split_dispatch([100, 1, 1], [64, 1, 1]),
+        [([0; 3], [2, 1, 1])]
+    );
+    assert_eq!(
+        split_dispatch([128, 9, 1], [64, 8, 1]),
+        [([0; 3], [2, 2, 1])]
+    );
+    assert!(split_dispatch([0, 1, 1], [64, 1, 1]).is_empty());
+
+    let max = MAX_DISPATCH_WORKGROUPS;
+    assert_eq!(
+        split_dispatch([max, 1, 1], [1, 1, 1]),
+        [([0; 3], [max, 1, 1])]
+    );
+    assert_eq!(
+        split_dispatch([(max + 1) * 2, 3, 1], [2, 1, 1]),
+        [([0; 3], [max, 3, 1]), ([max * 2, 0, 0], [1, 3, 1])]
+    );
+    assert_eq!(
+        split_dispatch([1, max + 2, max + 1], [1, 1, 1]),
+        [
+            ([0; 3], [1, max, max]),
+            ([0, max, 0], [1, 2, max]),
+            ([0, 0, max], [1, max, 1]),
+            ([0, max, max], [1, 2, 1]),
+        ]
+    );
+}
@@ -10,13 +10,30 @@
 /// This example specifies a list of three [VertexAttribute](crate::VertexAttribute),
 /// each with the given `shader_location` and `format`.
 /// Offsets are calculated automatically.
+///
+/// An attribute can be given an explicit offset with `@`, the following ones are placed
+/// right after it. The offset of the first attribute can also be set with `offset = N;`:
+/// ```
+/// # use wgpu::vertex_attr_array;
+/// let attrs = vertex_attr_array![offset = 8; 0 => Float3, 1 => Float2 @ 24, 2 => Float];
+/// assert_eq!(attrs[0].offset, 8);
+/// assert_eq!(attrs[1].offset, 24);
+/// assert_eq!(attrs[2].offset, 32);
+/// ```
+/// The macro can be evaluated in a constant context.
 #[macro_export]
 macro_rules! vertex_attr_array {
-    ($($loc:expr => $fmt:ident),* $(,)?) => {
-        $crate::vertex_attr_array!([] ; 0; $($loc => $fmt ,)*)
+    (offset = $start:expr; $($loc:expr => $fmt:ident $(@ $off:expr)?),* $(,)?) => {
+        $crate::vertex_attr_array!([] ; $start; $($loc => $fmt $(@ $off)? ,)*)
+    };
+    ($($loc:expr => $fmt:ident $(@ $off:expr)?),* $(,)?) => {
+        $crate::vertex_attr_array!([] ; 0; $($loc => $fmt $(@ $off)? ,)*)
     };
     ([$($t:expr,)*] ; $off:expr ;) => { [$($t,)*] };
-    ([$($t:expr,)*] ; $off:expr ; $loc:expr => $item:ident, $($ll:expr => $ii:ident ,)*) => {
+    ([$($t:expr,)*] ; $off:expr ; $loc:expr => $item:ident @ $explicit:expr, $($rest:tt)*) => {
+        $crate::vertex_attr_array!([$($t,)*] ; $explicit ; $loc => $item, $($rest)*)
+    };
+    ([$($t:expr,)*] ; $off:expr ; $loc:expr => $item:ident, $($rest:tt)*) => {
         $crate::vertex_attr_array!(
             [$($t,)*
             $crate::VertexAttribute {
@@ -25,7 +42,7 @@ macro_rules! vertex_attr_array {
                 shader_location: $loc,
             },];
             $off + $crate::VertexFormat :: $item.size();
-            $($ll => $ii ,)*
+            $($rest)*
         )
     };
 }
@@ -39,6 +56,48 @@ fn test_vertex_attr_array() {
     assert_eq!(attrs[0].shader_location, 0);
     assert_eq!(attrs[1].offset, std::mem::size_of::<(f32, f32)>() as u64);
     assert_eq!(attrs[1].shader_location, 3);
+
+    let attrs = vertex_attr_array![offset = 4; 0 => Float, 1 => Float4 @ 16, 2 => Uint];
+    assert_eq!(attrs[0].offset, 4);
+    assert_eq!(attrs[1].offset, 16);
+    assert_eq!(attrs[2].offset, 32);
+}
+
+/// Macro to produce a [VertexBufferLayout](crate::VertexBufferLayout).
+///
+/// The attributes use the syntax of [`vertex_attr_array!`]. The step mode defaults to `Vertex`.
+/// As the layout only borrows constants, it can be stored in a `static`:
+/// ```
+/// # use wgpu::vertex_buffer_layout;
+/// static LAYOUT: wgpu::VertexBufferLayout = vertex_buffer_layout!(
+///     array_stride: 32, step_mode: Instance;
+///     0 => Float3, 1 => Float2 @ 16,
+/// );
+/// assert_eq!(LAYOUT.attributes[1].offset, 16);
+/// ```
+#[macro_export]
+macro_rules! vertex_buffer_layout {
+    (array_stride: $stride:expr, step_mode: $step:ident; $($attrs:tt)*) => {
+        $crate::VertexBufferLayout {
+            array_stride: $stride as $crate::BufferAddress,
+            step_mode: $crate::InputStepMode :: $step,
+            attributes: &$crate::vertex_attr_array!($($attrs)*),
+        }
+    };
+    (array_stride: $stride:expr; $($attrs:tt)*) => {
+        $crate::vertex_buffer_layout!(array_stride: $stride, step_mode: Vertex; $($attrs)*)
+    };
+}
+
+#[test]
+fn test_vertex_buffer_layout() {
+    const LAYOUT: crate::VertexBufferLayout = vertex_buffer_layout!(
+        array_stride: std::mem::size_of::<[f32; 5]>();
+        0 => Float3, 1 => Float2,
+    );
+    assert_eq!(LAYOUT.array_stride, 20);
+    assert_eq!(LAYOUT.step_mode, crate::InputStepMode::Vertex);
+    assert_eq!(LAYOUT.attributes[1].offset, 12);
 }
 
 /// Macro to load a SPIR-V module statically.
@@ -0,0 +1,414 @@
+use std::{error, fmt};
+
+use crate::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferAddress, BufferBindingType,
+    BufferSize, BufferSlice, BufferUsage, Device, Sampler, ShaderStage, StorageTextureAccess,
+    TextureView, BIND_BUFFER_ALIGNMENT,
+};
+
+/// Mismatch between a [`BindGroupBuilder`] and its bind group layout.
+///
+/// Each variant carries the binding index it was detected on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindGroupBuilderError {
+    /// The layout has no entry with this binding index.
+    UnknownBinding(u32),
+    /// A resource was given twice for the same binding.
+    DuplicateBinding(u32),
+    /// No resource was given for a binding of the layout.
+    MissingBinding(u32),
+    /// The resource is not of the kind the layout expects.
+    WrongResourceKind {
+        /// Binding index of the resource.
+        binding: u32,
+        /// Kind of resource expected by the layout entry.
+        expected: &'static str,
+        /// Kind of the resource which was given.
+        found: &'static str,
+    },
+    /// The number of texture views doesn't match the `count` of the layout entry.
+    WrongArrayLength {
+        /// Binding index of the array.
+        binding: u32,
+        /// Number of texture views expected by the layout entry.
+        expected: u32,
+        /// Number of texture views which were given.
+        found: usize,
+    },
+    /// The layout entry isn't visible to any shader stage, or is writable from the vertex stage.
+    ///
+    /// This is a mistake in the layout descriptor rather than in the bindings, reported by the
+    /// builder as the device would reject the layout.
+    InvalidVisibility {
+        /// Binding index of the layout entry.
+        binding: u32,
+        /// Visibility of the layout entry.
+        visibility: ShaderStage,
+    },
+    /// The buffer wasn't created with the usage needed by the binding.
+    MissingBufferUsage {
+        /// Binding index of the buffer.
+        binding: u32,
+        /// Usage needed by the binding.
+        expected: BufferUsage,
+    },
+    /// The buffer offset isn't a multiple of [`BIND_BUFFER_ALIGNMENT`].
+    UnalignedBufferOffset {
+        /// Binding index of the buffer.
+        binding: u32,
+        /// Offset of the buffer range, in bytes.
+        offset: BufferAddress,
+    },
+    /// The buffer range goes past the end of the buffer.
+    BufferOutOfBounds {
+        /// Binding index of the buffer.
+        binding: u32,
+        /// End of the buffer range, in bytes, or `BufferAddress::MAX` if it overflows.
+        end: BufferAddress,
+        /// Size of the buffer, in bytes.
+        buffer_size: BufferAddress,
+    },
+    /// The buffer range is smaller than the `min_binding_size` of the layout entry.
+    BufferTooSmall {
+        /// Binding index of the buffer.
+        binding: u32,
+        /// Size of the buffer range, in bytes.
+        size: BufferAddress,
+        /// Minimum size required by the layout entry.
+        min_binding_size: BufferSize,
+    },
+}
+
+impl fmt::Display for BindGroupBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::UnknownBinding(binding) => {
+                write!(f, "binding {} is not part of the layout", binding)
+            }
+            Self::DuplicateBinding(binding) => write!(f, "binding {} is set twice", binding),
+            Self::MissingBinding(binding) => write!(f, "binding {} is not set", binding),
+            Self::WrongResourceKind {
+                binding,
+                expected,
+                found,
+            } => write!(
+                f,
+                "binding {} expects {}, but {} was given",
+                binding, expected, found
+            ),
+            Self::WrongArrayLength {
+                binding,
+                expected,
+                found,
+            } => write!(
+                f,
+                "binding {} expects {} texture views, but {} were given",
+                binding, expected, found
+            ),
+            Self::InvalidVisibility {
+                binding,
+                visibility,
+            } => write!(
+                f,
+                "binding {} has an invalid visibility {:?}",
+                binding, visibility
+            ),
+            Self::MissingBufferUsage { binding, expected } => write!(
+                f,
+                "the buffer of binding {} lacks the {:?} usage",
+                binding, expected
+            ),
+            Self::UnalignedBufferOffset { binding, offset } => write!(
+                f,
+                "the buffer offset {} of binding {} is not a multiple of {}",
+                offset, binding, BIND_BUFFER_ALIGNMENT
+            ),
+            Self::BufferOutOfBounds {
+                binding,
+                end,
+                buffer_size,
+            } => write!(
+                f,
+                "the buffer range of binding {} ends at {}, past the buffer size {}",
+                binding, end, buffer_size
+            ),
+            Self::BufferTooSmall {
+                binding,
+                size,
+                min_binding_size,
+            } => write!(
+                f,
+                "the buffer range of binding {} is {} bytes, but at least {} are required",
+                binding, size, min_binding_size
+            ),
+        }
+    }
+}
+
+impl error::Error for BindGroupBuilderError {}
+
+fn resource_kind(resource: &BindingResource) -> &'static str {
+    match *resource {
+        BindingResource::Buffer { .. } => "a buffer",
+        BindingResource::Sampler(_) => "a sampler",
+        BindingResource::TextureView(_) => "a texture view",
+        BindingResource::TextureViewArray(_) => "an array of texture views",
+    }
+}
+
+fn expected_kind(entry: &BindGroupLayoutEntry) -> &'static str {
+    match (entry.ty, entry.count) {
+        (BindingType::Buffer { .. }, _) => "a buffer",
+        (BindingType::Sampler { .. }, _) => "a sampler",
+        (_, Some(_)) => "an array of texture views",
+        (_, None) => "a texture view",
+    }
+}
+
+/// Returns whether shaders can write to the binding.
+fn is_writable(ty: &BindingType) -> bool {
+    match *ty {
+        BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only },
+            ..
+        } => !read_only,
+        BindingType::StorageTexture { access, .. } => access != StorageTextureAccess::ReadOnly,
+        _ => false,
+    }
+}
+
+/// Builder of a [`BindGroup`], checked against the descriptor of its layout.
+///
+/// The checks happen on the CPU when calling [`build`](Self::build), so that a mismatch is
+/// reported with the binding it concerns instead of as a validation error of the device.
+/// They cover the resource kinds, array lengths, and the usage, alignment and size of buffers.
+/// The visibilities of the layout descriptor are checked as well, since a layout the device
+/// rejects can't produce a bind group. Texture views and samplers don't expose their properties, so their sample
+/// types and dimensions are left to the device validation.
+///
+/// ```no_run
+/// # fn example(
+/// #     device: &wgpu::Device,
+/// #     uniforms: &wgpu::Buffer,
+/// #     view: &wgpu::TextureView,
+/// #     sampler: &wgpu::Sampler,
+/// # ) {
+/// let layout_descriptor = wgpu::BindGroupLayoutDescriptor {
+///     label: None,
+///     entries: &[/* uniforms at 0, texture at 1, sampler at 2 */],
+/// };
+/// let layout = device.create_bind_group_layout(&layout_descriptor);
+/// let bind_group = wgpu::util::BindGroupBuilder::new(&layout_descriptor)
+///     .buffer(0, uniforms.slice(..))
+///     .texture(1, view)
+///     .sampler(2, sampler)
+///     .build(device, &layout)
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BindGroupBuilder<'a> {
+    layout: &'a BindGroupLayoutDescriptor<'a>,
+    label: Option<&'a str>,
+    entries: Vec<BindGroupEntry<'a>>,
+}
+
+impl<'a> BindGroupBuilder<'a> {
+    /// Start a bind group matching the layout created from `layout`.
+    pub fn new(layout: &'a BindGroupLayoutDescriptor<'a>) -> Self {
+        Self {
+            layout,
+            label: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Debug label of the bind group.
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Bind a range of a buffer.
+    pub fn buffer(mut self, binding: u32, slice: BufferSlice<'a>) -> Self {
+        self.entries.push(BindGroupEntry {
+            binding,
+            resource: BindingResource::Buffer {
+                buffer: slice.buffer,
+                offset: slice.offset,
+                size: slice.size,
+            },
+        });
+        self
+    }
+
+    /// Bind a sampled or storage texture.
+    pub fn texture(mut self, binding: u32, view: &'a TextureView) -> Self {
+        self.entries.push(BindGroupEntry {
+            binding,
+            resource: BindingResource::TextureView(view),
+        });
+        self
+    }
+
+    /// Bind an array of textures, for a layout entry with a `count`.
+    pub fn texture_array(mut self, binding: u32, views: &'a [&'a TextureView]) -> Self {
+        self.entries.push(BindGroupEntry {
+            binding,
+            resource: BindingResource::TextureViewArray(views),
+        });
+        self
+    }
+
+    /// Bind a sampler.
+    pub fn sampler(mut self, binding: u32, sampler: &'a Sampler) -> Self {
+        self.entries.push(BindGroupEntry {
+            binding,
+            resource: BindingResource::Sampler(sampler),
+        });
+        self
+    }
+
+    fn check_entry(
+        layout_entry: &BindGroupLayoutEntry,
+        resource: &BindingResource,
+    ) -> Result<(), BindGroupBuilderError> {
+        let binding = layout_entry.binding;
+        let wrong_kind = || BindGroupBuilderError::WrongResourceKind {
+            binding,
+            expected: expected_kind(layout_entry),
+            found: resource_kind(resource),
+        };
+        match (layout_entry.ty, resource) {
+            (
+                BindingType::Buffer {
+                    ty,
+                    min_binding_size,
+                    ..
+                },
+                &BindingResource::Buffer {
+                    buffer,
+                    offset,
+                    size,
+                },
+            ) if layout_entry.count.is_none() => {
+                let expected = match ty {
+                    BufferBindingType::Uniform => BufferUsage::UNIFORM,
+                    BufferBindingType::Storage { .. } => BufferUsage::STORAGE,
+                };
+                if !buffer.usage.contains(expected) {
+                    return Err(BindGroupBuilderError::MissingBufferUsage { binding, expected });
+                }
+                if offset % BIND_BUFFER_ALIGNMENT != 0 {
+                    return Err(BindGroupBuilderError::UnalignedBufferOffset { binding, offset });
+                }
+                let buffer_size = buffer.map_context.lock().total_size;
+                let size = match size {
+                    Some(size) => size.get(),
+                    None => buffer_size.saturating_sub(offset),
+                };
+                match offset.checked_add(size) {
+                    Some(end) if end <= buffer_size => {}
+                    end => {
+                        return Err(BindGroupBuilderError::BufferOutOfBounds {
+                            binding,
+                            end: end.unwrap_or(BufferAddress::MAX),
+                            buffer_size,
+                        })
+                    }
+                }
+                match min_binding_size {
+                    Some(min_binding_size) if size < min_binding_size.get() => {
+                        Err(BindGroupBuilderError::BufferTooSmall {
+                            binding,
+                            size,
+                            min_binding_size,
+                        })
+                    }
+                    _ => Ok(()),
+                }
+            }
+            (BindingType::Sampler { .. }, &BindingResource::Sampler(_))
+                if layout_entry.count.is_none() =>
+            {
+                Ok(())
+            }
+            (BindingType::Texture { .. }, &BindingResource::TextureView(_))
+            | (BindingType::StorageTexture { .. }, &BindingResource::TextureView(_))
+                if layout_entry.count.is_none() =>
+            {
+                Ok(())
+            }
+            (BindingType::Texture { .. }, &BindingResource::TextureViewArray(views))
+            | (BindingType::StorageTexture { .. }, &BindingResource::TextureViewArray(views)) => {
+                match layout_entry.count {
+                    Some(count) if count.get() as usize == views.len() => Ok(()),
+                    Some(count) => Err(BindGroupBuilderError::WrongArrayLength {
+                        binding,
+                        expected: count.get(),
+                        found: views.len(),
+                    }),
+                    None => Err(wrong_kind()),
+                }
+            }
+            _ => Err(wrong_kind()),
+        }
+    }
+
+    /// Check the bindings against the layout descriptor, and the visibilities of the layout
+    /// descriptor itself.
+    pub fn validate(&self) -> Result<(), BindGroupBuilderError> {
+        for (index, entry) in self.entries.iter().enumerate() {
+            if self.entries[..index]
+                .iter()
+                .any(|other| other.binding == entry.binding)
+            {
+                return Err(BindGroupBuilderError::DuplicateBinding(entry.binding));
+            }
+            let layout_entry = self
+                .layout
+                .entries
+                .iter()
+                .find(|layout_entry| layout_entry.binding == entry.binding)
+                .ok_or(BindGroupBuilderError::UnknownBinding(entry.binding))?;
+            Self::check_entry(layout_entry, &entry.resource)?;
+        }
+
+        for layout_entry in self.layout.entries {
+            let visibility = layout_entry.visibility;
+            if visibility.is_empty()
+                || (visibility.contains(ShaderStage::VERTEX) && is_writable(&layout_entry.ty))
+            {
+                return Err(BindGroupBuilderError::InvalidVisibility {
+                    binding: layout_entry.binding,
+                    visibility,
+                });
+            }
+            if !self
+                .entries
+                .iter()
+                .any(|entry| entry.binding == layout_entry.binding)
+            {
+                return Err(BindGroupBuilderError::MissingBinding(layout_entry.binding));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the bindings, then create the bind group.
+    ///
+    /// `layout` must have been created from the descriptor given to [`new`](Self::new).
+    pub fn build(
+        &self,
+        device: &Device,
+        layout: &BindGroupLayout,
+    ) -> Result<BindGroup, BindGroupBuilderError> {
+        self.validate()?;
+        Ok(device.create_bind_group(&BindGroupDescriptor {
+            label: self.label,
+            layout,
+            entries: &self.entries,
+        }))
+    }
+}
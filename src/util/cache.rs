@@ -0,0 +1,395 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    num::NonZeroU8,
+    sync::{Arc, Weak},
+};
+
+use crate::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindingResource,
+    Buffer, BufferAddress, BufferSize, CompareFunction, Device, FilterMode, Label, Sampler,
+    SamplerBorderColor, SamplerDescriptor, TextureView,
+};
+
+/// Hashable form of a [`SamplerDescriptor`], leaving out its label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerKey {
+    address_modes: [AddressMode; 3],
+    filters: [FilterMode; 3],
+    lod_clamps: [u32; 2],
+    compare: Option<CompareFunction>,
+    anisotropy_clamp: Option<NonZeroU8>,
+    border_color: Option<SamplerBorderColor>,
+}
+
+impl From<&SamplerDescriptor<'_>> for SamplerKey {
+    fn from(desc: &SamplerDescriptor) -> Self {
+        Self {
+            address_modes: [
+                desc.address_mode_u,
+                desc.address_mode_v,
+                desc.address_mode_w,
+            ],
+            filters: [desc.mag_filter, desc.min_filter, desc.mipmap_filter],
+            lod_clamps: [desc.lod_min_clamp.to_bits(), desc.lod_max_clamp.to_bits()],
+            compare: desc.compare,
+            anisotropy_clamp: desc.anisotropy_clamp,
+            border_color: desc.border_color,
+        }
+    }
+}
+
+/// Cache of samplers, sharing one sampler between identical descriptors.
+///
+/// The label of a descriptor is only used when its sampler gets created.
+#[derive(Debug, Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerKey, Arc<Sampler>>,
+}
+
+impl SamplerCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the sampler matching `desc`, creating it if needed.
+    pub fn get(&mut self, device: &Device, desc: &SamplerDescriptor) -> Arc<Sampler> {
+        let sampler = self
+            .samplers
+            .entry(SamplerKey::from(desc))
+            .or_insert_with(|| Arc::new(device.create_sampler(desc)));
+        Arc::clone(sampler)
+    }
+
+    /// Number of cached samplers.
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    /// Returns `true` if no sampler is cached.
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+
+    /// Drop all the cached samplers.
+    pub fn clear(&mut self) {
+        self.samplers.clear();
+    }
+}
+
+/// Weak reference to a resource bound by a cached bind group.
+///
+/// As the cache holding it keeps the allocation of the resource, its address identifies the
+/// resource.
+#[derive(Clone)]
+struct Handle {
+    identity: usize,
+    resource: Weak<dyn Any + Send + Sync>,
+}
+
+impl Handle {
+    fn new<T: Any + Send + Sync>(resource: &Arc<T>) -> Self {
+        let identity = address(&**resource);
+        let resource: Arc<dyn Any + Send + Sync> = Arc::clone(resource) as _;
+        Self {
+            identity,
+            resource: Arc::downgrade(&resource),
+        }
+    }
+
+    fn identity(&self) -> usize {
+        self.identity
+    }
+
+    fn is_alive(&self) -> bool {
+        self.resource.strong_count() != 0
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({:#x})", self.identity())
+    }
+}
+
+fn address<T>(resource: &T) -> usize {
+    resource as *const T as usize
+}
+
+/// Resource bound by a [`BindGroupCacheEntry`].
+///
+/// Unlike [`BindingResource`], the resources are shared, so that the cache can tell when they
+/// are dropped.
+#[derive(Clone, Copy, Debug)]
+pub enum CachedBindingResource<'a> {
+    /// Binding is backed by a buffer.
+    Buffer {
+        /// The buffer to bind.
+        buffer: &'a Arc<Buffer>,
+        /// Base offset of the buffer.
+        offset: BufferAddress,
+        /// Size of the binding, or `None` for using the rest of the buffer.
+        size: Option<BufferSize>,
+    },
+    /// Binding is a sampler.
+    Sampler(&'a Arc<Sampler>),
+    /// Binding is backed by a texture.
+    TextureView(&'a Arc<TextureView>),
+    /// Binding is backed by an array of textures.
+    TextureViewArray(&'a [Arc<TextureView>]),
+}
+
+/// Entry of a bind group created through a [`BindGroupCache`].
+#[derive(Clone, Copy, Debug)]
+pub struct BindGroupCacheEntry<'a> {
+    /// Slot for which binding provides resource.
+    pub binding: u32,
+    /// Resource to attach to the binding.
+    pub resource: CachedBindingResource<'a>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ResourceKey {
+    Buffer {
+        buffer: usize,
+        offset: BufferAddress,
+        size: Option<BufferSize>,
+    },
+    Sampler(usize),
+    TextureView(usize),
+    TextureViewArray(Vec<usize>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct BindGroupKey {
+    layout: usize,
+    entries: Vec<(u32, ResourceKey)>,
+}
+
+#[derive(Debug)]
+struct CachedBindGroup {
+    bind_group: Arc<BindGroup>,
+    /// The layout and every bound resource.
+    handles: Vec<Handle>,
+}
+
+impl CachedBindGroup {
+    fn is_alive(&self) -> bool {
+        self.handles.iter().all(Handle::is_alive)
+    }
+
+    fn binds(&self, identity: usize) -> bool {
+        self.handles
+            .iter()
+            .any(|handle| handle.identity() == identity)
+    }
+}
+
+/// Cache of bind groups, sharing one bind group between descriptors binding the same
+/// resources to the same layout.
+///
+/// Resources are compared by identity, along with the bound buffer ranges. The cache only keeps
+/// weak references to them, and a bind group is evicted once its layout or any of its
+/// resources is dropped. Stale bind groups are released when a new one is inserted, or by
+/// calling [`purge`](Self::purge).
+///
+/// Destroying a buffer or a texture isn't observed by the cache: the bind groups using them
+/// must be evicted with [`invalidate_buffer`](Self::invalidate_buffer) and
+/// [`invalidate_texture_view`](Self::invalidate_texture_view).
+#[derive(Debug, Default)]
+pub struct BindGroupCache {
+    bind_groups: HashMap<BindGroupKey, CachedBindGroup>,
+}
+
+impl BindGroupCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(
+        layout: &Arc<BindGroupLayout>,
+        entries: &[BindGroupCacheEntry],
+    ) -> (BindGroupKey, Vec<Handle>) {
+        let mut handles = vec![Handle::new(layout)];
+        let mut track = |handle: Handle| {
+            let identity = handle.identity();
+            handles.push(handle);
+            identity
+        };
+        let mut entries = entries
+            .iter()
+            .map(|entry| {
+                let resource = match entry.resource {
+                    CachedBindingResource::Buffer {
+                        buffer,
+                        offset,
+                        size,
+                    } => ResourceKey::Buffer {
+                        buffer: track(Handle::new(buffer)),
+                        offset,
+                        size,
+                    },
+                    CachedBindingResource::Sampler(sampler) => {
+                        ResourceKey::Sampler(track(Handle::new(sampler)))
+                    }
+                    CachedBindingResource::TextureView(view) => {
+                        ResourceKey::TextureView(track(Handle::new(view)))
+                    }
+                    CachedBindingResource::TextureViewArray(views) => {
+                        ResourceKey::TextureViewArray(
+                            views.iter().map(|view| track(Handle::new(view))).collect(),
+                        )
+                    }
+                };
+                (entry.binding, resource)
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|&(binding, _)| binding);
+        let key = BindGroupKey {
+            layout: handles[0].identity(),
+            entries,
+        };
+        (key, handles)
+    }
+
+    /// Return the bind group binding `entries` to `layout`, creating it if needed.
+    ///
+    /// The label is only used when the bind group gets created.
+    pub fn get(
+        &mut self,
+        device: &Device,
+        label: Label,
+        layout: &Arc<BindGroupLayout>,
+        entries: &[BindGroupCacheEntry],
+    ) -> Arc<BindGroup> {
+        let (key, handles) = Self::key(layout, entries);
+        if let Some(cached) = self.bind_groups.get(&key) {
+            if cached.is_alive() {
+                return Arc::clone(&cached.bind_group);
+            }
+        }
+
+        self.purge();
+        let views = entries
+            .iter()
+            .map(|entry| match entry.resource {
+                CachedBindingResource::TextureViewArray(views) => {
+                    views.iter().map(|view| &**view).collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let entries = entries
+            .iter()
+            .zip(&views)
+            .map(|(entry, views)| BindGroupEntry {
+                binding: entry.binding,
+                resource: match entry.resource {
+                    CachedBindingResource::Buffer {
+                        buffer,
+                        offset,
+                        size,
+                    } => BindingResource::Buffer {
+                        buffer,
+                        offset,
+                        size,
+                    },
+                    CachedBindingResource::Sampler(sampler) => BindingResource::Sampler(sampler),
+                    CachedBindingResource::TextureView(view) => BindingResource::TextureView(view),
+                    CachedBindingResource::TextureViewArray(_) => {
+                        BindingResource::TextureViewArray(views)
+                    }
+                },
+            })
+            .collect::<Vec<_>>();
+        let bind_group = Arc::new(device.create_bind_group(&BindGroupDescriptor {
+            label,
+            layout,
+            entries: &entries,
+        }));
+        self.bind_groups.insert(
+            key,
+            CachedBindGroup {
+                bind_group: Arc::clone(&bind_group),
+                handles,
+            },
+        );
+        bind_group
+    }
+
+    /// Release the bind groups referencing dropped resources.
+    pub fn purge(&mut self) {
+        self.bind_groups.retain(|_, cached| cached.is_alive());
+    }
+
+    /// Release the bind groups binding `buffer`, before it gets destroyed.
+    pub fn invalidate_buffer(&mut self, buffer: &Buffer) {
+        let identity = address(buffer);
+        self.bind_groups.retain(|_, cached| !cached.binds(identity));
+    }
+
+    /// Release the bind groups binding `view`, before its texture gets destroyed.
+    pub fn invalidate_texture_view(&mut self, view: &TextureView) {
+        let identity = address(view);
+        self.bind_groups.retain(|_, cached| !cached.binds(identity));
+    }
+
+    /// Number of cached bind groups, including stale ones which weren't purged yet.
+    pub fn len(&self) -> usize {
+        self.bind_groups.len()
+    }
+
+    /// Returns `true` if no bind group is cached.
+    pub fn is_empty(&self) -> bool {
+        self.bind_groups.is_empty()
+    }
+
+    /// Drop all the cached bind groups.
+    pub fn clear(&mut self) {
+        self.bind_groups.clear();
+    }
+}
+
+#[test]
+fn sampler_keys() {
+    let desc = SamplerDescriptor {
+        label: Some("a"),
+        mag_filter: FilterMode::Linear,
+        ..Default::default()
+    };
+    let relabeled = SamplerDescriptor {
+        label: Some("b"),
+        ..desc.clone()
+    };
+    assert_eq!(SamplerKey::from(&desc), SamplerKey::from(&relabeled));
+
+    let clamped = SamplerDescriptor {
+        lod_max_clamp: 4.0,
+        ..desc.clone()
+    };
+    assert_ne!(SamplerKey::from(&desc), SamplerKey::from(&clamped));
+    let compared = SamplerDescriptor {
+        compare: Some(CompareFunction::Less),
+        ..desc
+    };
+    assert_ne!(SamplerKey::from(&clamped), SamplerKey::from(&compared));
+}
+
+#[test]
+fn handles() {
+    let resource = Arc::new(0u32);
+    let handle = Handle::new(&resource);
+    assert!(handle.is_alive());
+    assert_eq!(handle.identity(), address(&*resource));
+
+    let clone = handle.clone();
+    drop(resource);
+    assert!(!handle.is_alive());
+    // The allocation is kept, so the identity can't be reused
+    assert_eq!(clone.identity(), handle.identity());
+    let other = Arc::new(0u32);
+    assert_ne!(address(&*other), handle.identity());
+}
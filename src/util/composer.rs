@@ -0,0 +1,471 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    error, fmt, fs, io,
+    path::PathBuf,
+};
+
+use crate::{Error, Features, ShaderFlags, ShaderModuleDescriptor, ShaderSource};
+
+/// Names of the features exposed as `FEATURE_*` defines by [`ShaderComposer::with_features`].
+const FEATURE_DEFINES: &[(Features, &str)] = &[
+    (Features::DEPTH_CLAMPING, "FEATURE_DEPTH_CLAMPING"),
+    (
+        Features::TEXTURE_COMPRESSION_BC,
+        "FEATURE_TEXTURE_COMPRESSION_BC",
+    ),
+    (Features::TIMESTAMP_QUERY, "FEATURE_TIMESTAMP_QUERY"),
+    (
+        Features::PIPELINE_STATISTICS_QUERY,
+        "FEATURE_PIPELINE_STATISTICS_QUERY",
+    ),
+    (
+        Features::MAPPABLE_PRIMARY_BUFFERS,
+        "FEATURE_MAPPABLE_PRIMARY_BUFFERS",
+    ),
+    (
+        Features::SAMPLED_TEXTURE_BINDING_ARRAY,
+        "FEATURE_SAMPLED_TEXTURE_BINDING_ARRAY",
+    ),
+    (
+        Features::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING,
+        "FEATURE_SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING",
+    ),
+    (
+        Features::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+        "FEATURE_SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING",
+    ),
+    (
+        Features::UNSIZED_BINDING_ARRAY,
+        "FEATURE_UNSIZED_BINDING_ARRAY",
+    ),
+    (Features::MULTI_DRAW_INDIRECT, "FEATURE_MULTI_DRAW_INDIRECT"),
+    (
+        Features::MULTI_DRAW_INDIRECT_COUNT,
+        "FEATURE_MULTI_DRAW_INDIRECT_COUNT",
+    ),
+    (Features::PUSH_CONSTANTS, "FEATURE_PUSH_CONSTANTS"),
+    (
+        Features::ADDRESS_MODE_CLAMP_TO_BORDER,
+        "FEATURE_ADDRESS_MODE_CLAMP_TO_BORDER",
+    ),
+    (
+        Features::NON_FILL_POLYGON_MODE,
+        "FEATURE_NON_FILL_POLYGON_MODE",
+    ),
+    (
+        Features::TEXTURE_COMPRESSION_ETC2,
+        "FEATURE_TEXTURE_COMPRESSION_ETC2",
+    ),
+    (
+        Features::TEXTURE_COMPRESSION_ASTC_LDR,
+        "FEATURE_TEXTURE_COMPRESSION_ASTC_LDR",
+    ),
+    (
+        Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+        "FEATURE_TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES",
+    ),
+    (Features::SHADER_FLOAT64, "FEATURE_SHADER_FLOAT64"),
+    (
+        Features::VERTEX_ATTRIBUTE_64BIT,
+        "FEATURE_VERTEX_ATTRIBUTE_64BIT",
+    ),
+];
+
+/// Error produced while composing a WGSL shader.
+#[derive(Debug)]
+pub enum ComposeError {
+    /// An `#include` names a module that is neither registered nor found under the root.
+    MissingInclude {
+        /// Name given to `#include`.
+        name: String,
+        /// Location of the directive.
+        location: SourceLocation,
+    },
+    /// A module includes itself, directly or through other modules.
+    RecursiveInclude {
+        /// Name of the module that closes the cycle.
+        name: String,
+        /// Location of the directive.
+        location: SourceLocation,
+    },
+    /// A preprocessor directive is malformed, or `#else`/`#endif` has no matching `#ifdef`.
+    InvalidDirective {
+        /// Text of the offending line.
+        directive: String,
+        /// Location of the directive.
+        location: SourceLocation,
+    },
+    /// A file ends with an `#ifdef`/`#ifndef` that was never closed.
+    UnterminatedConditional {
+        /// Location of the opening directive.
+        location: SourceLocation,
+    },
+    /// Reading an included file from disk failed.
+    Io {
+        /// Path that was read.
+        path: PathBuf,
+        /// Underlying error.
+        source: io::Error,
+    },
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingInclude { name, location } => {
+                write!(f, "{}: unable to resolve include {:?}", location, name)
+            }
+            Self::RecursiveInclude { name, location } => {
+                write!(f, "{}: module {:?} includes itself", location, name)
+            }
+            Self::InvalidDirective {
+                directive,
+                location,
+            } => write!(f, "{}: invalid directive `{}`", location, directive),
+            Self::UnterminatedConditional { location } => {
+                write!(f, "{}: conditional block is never closed", location)
+            }
+            Self::Io { path, source } => write!(f, "unable to read {:?}: {}", path, source),
+        }
+    }
+}
+
+impl error::Error for ComposeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Position of a line in one of the files that make up a [`ComposedShader`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// Name of the module or file the line comes from.
+    pub file: String,
+    /// Line number within that file, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// WGSL source produced by [`ShaderComposer::compose`].
+///
+/// Keeps track of where every line of the flattened source originally came from,
+/// so that errors reported against it can be pointed back at the right file.
+#[derive(Clone, Debug)]
+pub struct ComposedShader {
+    source: String,
+    lines: Vec<SourceLocation>,
+}
+
+impl ComposedShader {
+    /// The flattened WGSL source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Look up where `line` (starting at 1) of the flattened source came from.
+    pub fn map_line(&self, line: usize) -> Option<&SourceLocation> {
+        line.checked_sub(1).and_then(|index| self.lines.get(index))
+    }
+
+    /// Creates a [`ShaderModuleDescriptor`] borrowing the flattened source.
+    pub fn descriptor<'a>(&'a self, label: Option<&'a str>) -> ShaderModuleDescriptor<'a> {
+        ShaderModuleDescriptor {
+            label,
+            source: ShaderSource::Wgsl(Cow::Borrowed(&self.source)),
+            flags: ShaderFlags::VALIDATION,
+        }
+    }
+
+    /// Rewrites `line N` references in an error description so they name the original file.
+    ///
+    /// Meant to be used from a handler installed with
+    /// [`Device::on_uncaptured_error`](crate::Device::on_uncaptured_error)
+    /// while this shader is being created.
+    pub fn remap_error(&self, error: Error) -> Error {
+        match error {
+            Error::ValidationError {
+                source,
+                description,
+            } => Error::ValidationError {
+                description: self.remap_description(&description),
+                source,
+            },
+            other => other,
+        }
+    }
+
+    fn remap_description(&self, description: &str) -> String {
+        const KEYWORD: &str = "line ";
+        let mut result = String::with_capacity(description.len());
+        let mut rest = description;
+        while let Some(pos) = rest.find(KEYWORD) {
+            let (before, after) = rest.split_at(pos + KEYWORD.len());
+            result.push_str(before);
+            // only whole words count, so that "pipeline 3" is left alone
+            let is_word = rest[..pos]
+                .chars()
+                .next_back()
+                .map_or(true, |c| !c.is_alphanumeric() && c != '_');
+            let digits = if is_word {
+                after
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(after.len())
+            } else {
+                0
+            };
+            match after[..digits]
+                .parse::<usize>()
+                .ok()
+                .and_then(|line| self.map_line(line))
+            {
+                Some(location) => {
+                    // replace the "line " we just copied with the full location
+                    result.truncate(result.len() - KEYWORD.len());
+                    result.push_str(&location.to_string());
+                }
+                None => result.push_str(&after[..digits]),
+            }
+            rest = &after[digits..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// Flattens WGSL sources split over several files into a single module.
+///
+/// The following directives are recognized, each on a line of its own:
+/// - `#include "name"`: pastes the named module. Modules registered with
+///   [`ShaderComposer::add_module`] take precedence over files under the root directory.
+///   Every module is only included once per composition.
+/// - `#define NAME` and `#undef NAME`: toggles a flag for the rest of the composition.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif`: conditional blocks.
+///
+/// Defines are only used for conditional compilation, no textual substitution is performed.
+///
+/// ```
+/// # use wgpu::util::ShaderComposer;
+/// let mut composer = ShaderComposer::new();
+/// composer.add_module("lighting", "fn ambient() -> f32 { return 0.1; }");
+/// let shader = composer
+///     .compose("main", "#include \"lighting\"\n[[stage(fragment)]] fn fs_main() {}")
+///     .unwrap();
+/// assert_eq!(shader.map_line(1).unwrap().file, "lighting");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ShaderComposer {
+    modules: HashMap<String, String>,
+    root: Option<PathBuf>,
+    defines: HashSet<String>,
+}
+
+impl ShaderComposer {
+    /// Creates a composer without any modules or defines.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the directory that `#include` falls back to for modules that weren't registered.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Defines `FEATURE_<NAME>` for every feature in `features`.
+    ///
+    /// Typically called with [`Device::features`](crate::Device::features).
+    pub fn with_features(mut self, features: Features) -> Self {
+        for &(feature, name) in FEATURE_DEFINES {
+            if features.contains(feature) {
+                self.defines.insert(name.to_string());
+            }
+        }
+        self
+    }
+
+    /// Registers a module that can be pulled in with `#include "name"`.
+    pub fn add_module(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.modules.insert(name.into(), source.into());
+    }
+
+    /// Defines a flag before composition starts.
+    pub fn define(&mut self, name: impl Into<String>) {
+        self.defines.insert(name.into());
+    }
+
+    /// Removes a flag previously set with [`ShaderComposer::define`].
+    pub fn undefine(&mut self, name: &str) {
+        self.defines.remove(name);
+    }
+
+    /// Flattens `source` and everything it includes.
+    ///
+    /// `name` identifies `source` in the line mapping of the result.
+    pub fn compose(&self, name: &str, source: &str) -> Result<ComposedShader, ComposeError> {
+        let mut state = ComposeState {
+            output: ComposedShader {
+                source: String::with_capacity(source.len()),
+                lines: Vec::new(),
+            },
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            stack: vec![name.to_string()],
+        };
+        self.compose_file(&mut state, name, source)?;
+        Ok(state.output)
+    }
+
+    fn load(&self, name: &str, location: &SourceLocation) -> Result<Cow<str>, ComposeError> {
+        if let Some(source) = self.modules.get(name) {
+            return Ok(Cow::Borrowed(source));
+        }
+        match self.root {
+            Some(ref root) => {
+                let path = root.join(name);
+                match fs::read_to_string(&path) {
+                    Ok(source) => Ok(Cow::Owned(source)),
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                        Err(ComposeError::MissingInclude {
+                            name: name.to_string(),
+                            location: location.clone(),
+                        })
+                    }
+                    Err(source) => Err(ComposeError::Io { path, source }),
+                }
+            }
+            None => Err(ComposeError::MissingInclude {
+                name: name.to_string(),
+                location: location.clone(),
+            }),
+        }
+    }
+
+    fn compose_file(
+        &self,
+        state: &mut ComposeState,
+        file: &str,
+        source: &str,
+    ) -> Result<(), ComposeError> {
+        // Each entry is (location of the opening directive, branch active, parent active).
+        let mut conditionals: Vec<(SourceLocation, bool, bool)> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let location = SourceLocation {
+                file: file.to_string(),
+                line: index + 1,
+            };
+            let active = conditionals.last().map_or(true, |&(_, active, _)| active);
+            let trimmed = line.trim();
+            let mut words = trimmed.splitn(2, char::is_whitespace);
+            let directive = words.next().unwrap_or_default();
+            let argument = words.next().map(str::trim).unwrap_or_default();
+            let invalid = || ComposeError::InvalidDirective {
+                directive: trimmed.to_string(),
+                location: location.clone(),
+            };
+
+            match directive {
+                "#ifdef" | "#ifndef" => {
+                    if argument.is_empty() {
+                        return Err(invalid());
+                    }
+                    let defined = state.defines.contains(argument);
+                    let taken = defined == (directive == "#ifdef");
+                    conditionals.push((location.clone(), active && taken, active));
+                }
+                "#else" => match conditionals.last_mut() {
+                    Some((_, branch, parent)) => *branch = *parent && !*branch,
+                    None => return Err(invalid()),
+                },
+                "#endif" => {
+                    if conditionals.pop().is_none() {
+                        return Err(invalid());
+                    }
+                }
+                _ if !active => {}
+                "#define" => {
+                    if argument.is_empty() {
+                        return Err(invalid());
+                    }
+                    state.defines.insert(argument.to_string());
+                }
+                "#undef" => {
+                    state.defines.remove(argument);
+                }
+                "#include" => {
+                    let name = argument
+                        .strip_prefix('"')
+                        .and_then(|arg| arg.strip_suffix('"'))
+                        .ok_or_else(invalid)?;
+                    if state.stack.iter().any(|parent| parent == name) {
+                        return Err(ComposeError::RecursiveInclude {
+                            name: name.to_string(),
+                            location,
+                        });
+                    }
+                    if state.included.insert(name.to_string()) {
+                        let included = self.load(name, &location)?;
+                        state.stack.push(name.to_string());
+                        self.compose_file(state, name, &included)?;
+                        state.stack.pop();
+                    }
+                }
+                _ => {
+                    state.output.source.push_str(line);
+                    state.output.source.push('\n');
+                    state.output.lines.push(location);
+                }
+            }
+        }
+
+        match conditionals.pop() {
+            Some((location, _, _)) => Err(ComposeError::UnterminatedConditional { location }),
+            None => Ok(()),
+        }
+    }
+}
+
+struct ComposeState {
+    output: ComposedShader,
+    defines: HashSet<String>,
+    included: HashSet<String>,
+    stack: Vec<String>,
+}
+
+#[test]
+fn test_shader_composer() {
+    let mut composer = ShaderComposer::new().with_features(Features::PUSH_CONSTANTS);
+    composer.add_module("common", "#define COMMON\nlet a: f32 = 1.0;");
+    let shader = composer
+        .compose(
+            "main",
+            "#include \"common\"\n#include \"common\"\n#ifdef FEATURE_PUSH_CONSTANTS\npush\n#else\nuniform\n#endif\n#ifndef COMMON\nmissing\n#endif\nbody",
+        )
+        .unwrap();
+    assert_eq!(shader.source(), "let a: f32 = 1.0;\npush\nbody\n");
+    assert_eq!(
+        shader.map_line(1),
+        Some(&SourceLocation {
+            file: "common".to_string(),
+            line: 2,
+        })
+    );
+    assert_eq!(shader.map_line(3).unwrap().line, 11);
+    assert_eq!(
+        shader.remap_description("error at line 2, column 4"),
+        "error at main:4, column 4"
+    );
+    assert_eq!(
+        shader.remap_description("in pipeline 2, at line 2"),
+        "in pipeline 2, at main:4"
+    );
+}
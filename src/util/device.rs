@@ -35,6 +35,7 @@ pub trait DeviceExt {
 }
 
 impl DeviceExt for crate::Device {
+    #[track_caller]
     fn create_buffer_init(&self, descriptor: &BufferInitDescriptor<'_>) -> crate::Buffer {
         let unpadded_size = descriptor.contents.len() as crate::BufferAddress;
         let padding = crate::COPY_BUFFER_ALIGNMENT - unpadded_size % crate::COPY_BUFFER_ALIGNMENT;
@@ -64,6 +65,7 @@ impl DeviceExt for crate::Device {
         buffer
     }
 
+    #[track_caller]
     fn create_texture_with_data(
         &self,
         queue: &crate::Queue,
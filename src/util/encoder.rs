@@ -2,6 +2,7 @@ use std::ops::Range;
 
 use wgt::{BufferAddress, DynamicOffset, IndexFormat};
 
+use super::{DrawIndexedIndirectArgs, DrawIndirectArgs};
 use crate::{BindGroup, Buffer, BufferSlice, RenderBundleEncoder, RenderPass, RenderPipeline};
 
 /// Methods shared by `RenderPass` and `RenderBundleEncoder`
@@ -87,6 +88,49 @@ pub trait RenderEncoder<'a> {
         indirect_offset: BufferAddress,
     );
 
+    /// Disptaches multiple draw calls from the active vertex buffer(s) based on the contents of the `indirect_buffer`.
+    /// `count` draw calls are issued.
+    ///
+    /// [`wgt::Features::MULTI_DRAW_INDIRECT`] must be enabled on the device in order to call this function.
+    /// See [`RenderPass::multi_draw_indirect`] for the structure expected in `indirect_buffer`.
+    ///
+    /// The default implementation issues `count` separate indirect draws, so that implementors
+    /// written before this method existed keep compiling.
+    fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        for index in 0..count as BufferAddress {
+            self.draw_indirect(
+                indirect_buffer,
+                indirect_offset + index * DrawIndirectArgs::SIZE,
+            );
+        }
+    }
+
+    /// Disptaches multiple draw calls from the active index buffer and the active vertex buffers,
+    /// based on the contents of the `indirect_buffer`. `count` draw calls are issued.
+    ///
+    /// [`wgt::Features::MULTI_DRAW_INDIRECT`] must be enabled on the device in order to call this function.
+    /// See [`RenderPass::multi_draw_indexed_indirect`] for the structure expected in `indirect_buffer`.
+    ///
+    /// The default implementation issues `count` separate indexed indirect draws.
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        for index in 0..count as BufferAddress {
+            self.draw_indexed_indirect(
+                indirect_buffer,
+                indirect_offset + index * DrawIndexedIndirectArgs::SIZE,
+            );
+        }
+    }
+
     /// [`wgt::Features::PUSH_CONSTANTS`] must be enabled on the device in order to call this function.
     ///
     /// Set push constant data.
@@ -165,6 +209,26 @@ impl<'a> RenderEncoder<'a> for RenderPass<'a> {
         Self::draw_indexed_indirect(self, indirect_buffer, indirect_offset);
     }
 
+    #[inline(always)]
+    fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        Self::multi_draw_indirect(self, indirect_buffer, indirect_offset, count);
+    }
+
+    #[inline(always)]
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        Self::multi_draw_indexed_indirect(self, indirect_buffer, indirect_offset, count);
+    }
+
     #[inline(always)]
     fn set_push_constants(&mut self, stages: wgt::ShaderStage, offset: u32, data: &[u8]) {
         Self::set_push_constants(self, stages, offset, data);
@@ -216,6 +280,26 @@ impl<'a> RenderEncoder<'a> for RenderBundleEncoder<'a> {
         Self::draw_indexed_indirect(self, indirect_buffer, indirect_offset);
     }
 
+    #[inline(always)]
+    fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        Self::multi_draw_indirect(self, indirect_buffer, indirect_offset, count);
+    }
+
+    #[inline(always)]
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        Self::multi_draw_indexed_indirect(self, indirect_buffer, indirect_offset, count);
+    }
+
     #[inline(always)]
     fn set_push_constants(&mut self, stages: wgt::ShaderStage, offset: u32, data: &[u8]) {
         Self::set_push_constants(self, stages, offset, data);
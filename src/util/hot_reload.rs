@@ -0,0 +1,411 @@
+use std::{
+    borrow::Cow,
+    error, fmt, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::SpirvError;
+use crate::{
+    ComputePipeline, Device, Error, ErrorFilter, RenderPipeline, ShaderFlags, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource,
+};
+
+/// Error produced while reloading shaders and pipelines from disk.
+#[derive(Debug)]
+pub enum ReloadError {
+    /// Reading a shader file failed.
+    Io {
+        /// Path of the shader file.
+        path: PathBuf,
+        /// Underlying error.
+        source: io::Error,
+    },
+    /// A `.spv` file is not a valid SPIR-V binary.
+    Spirv {
+        /// Path of the shader file.
+        path: PathBuf,
+        /// Underlying error.
+        source: SpirvError,
+    },
+    /// A shader file failed to compile. The previous module is kept.
+    Shader {
+        /// Path of the shader file.
+        path: PathBuf,
+        /// Error reported by the device.
+        error: Error,
+    },
+    /// A dependent pipeline failed validation. The previous pipeline is kept.
+    Pipeline {
+        /// Label given when the pipeline was registered.
+        label: String,
+        /// Error reported by the device.
+        error: Error,
+    },
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "unable to read {:?}: {}", path, source),
+            Self::Spirv { path, source } => write!(f, "invalid SPIR-V in {:?}: {}", path, source),
+            Self::Shader { path, error } => write!(f, "reloading {:?} failed: {}", path, error),
+            Self::Pipeline { label, error } => {
+                write!(f, "rebuilding pipeline {:?} failed: {}", label, error)
+            }
+        }
+    }
+}
+
+impl error::Error for ReloadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Spirv { source, .. } => Some(source),
+            Self::Shader { error, .. } | Self::Pipeline { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Handle to a shader watched by [`HotReload`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderHandle(usize);
+
+/// Handle to a render pipeline managed by [`HotReload`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderPipelineHandle(usize);
+
+/// Handle to a compute pipeline managed by [`HotReload`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ComputePipelineHandle(usize);
+
+struct WatchedShader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    module: ShaderModule,
+}
+
+struct Managed<P> {
+    label: String,
+    shaders: Vec<ShaderHandle>,
+    build: Box<dyn Fn(&Device, &[&ShaderModule]) -> P>,
+    pipeline: P,
+}
+
+/// Reloads WGSL and SPIR-V shaders from disk when they change, and rebuilds the pipelines using them.
+///
+/// Files ending in `.spv` are loaded as SPIR-V, everything else as WGSL. Pipelines are
+/// registered together with a closure that creates them from the current shader modules.
+/// When a reloaded shader or a rebuilt pipeline fails validation, the error is caught
+/// through an [error scope](Device::push_error_scope) and the previous object is kept.
+///
+/// Call [`HotReload::update`] regularly, for example once per frame:
+///
+/// ```no_run
+/// # async fn example(device: &wgpu::Device) {
+/// use wgpu::util::HotReload;
+///
+/// let mut hot_reload = HotReload::new();
+/// let shader = hot_reload.watch_shader(device, "shader.wgsl").unwrap();
+/// let build = |device: &wgpu::Device, modules: &[&wgpu::ShaderModule]| {
+///     device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+///         label: Some("compute"),
+///         layout: None,
+///         module: modules[0],
+///         entry_point: "main",
+///         constants: &[],
+///     })
+/// };
+/// let pipeline = hot_reload.add_compute_pipeline("compute", &[shader], build, device);
+///
+/// for error in hot_reload.update(device).await {
+///     eprintln!("{}", error);
+/// }
+/// let pipeline = hot_reload.compute_pipeline(pipeline);
+/// # }
+/// ```
+#[derive(Default)]
+pub struct HotReload {
+    shaders: Vec<WatchedShader>,
+    render_pipelines: Vec<Managed<RenderPipeline>>,
+    compute_pipelines: Vec<Managed<ComputePipeline>>,
+}
+
+impl fmt::Debug for HotReload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotReload")
+            .field(
+                "shaders",
+                &self.shaders.iter().map(|s| &s.path).collect::<Vec<_>>(),
+            )
+            .field("render_pipelines", &self.render_pipelines.len())
+            .field("compute_pipelines", &self.compute_pipelines.len())
+            .finish()
+    }
+}
+
+impl HotReload {
+    /// Creates an empty hot reloader.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the shader at `path` and starts watching it for changes.
+    ///
+    /// Errors in the initial compilation go to the device's uncaptured error handler,
+    /// like any other resource creation.
+    pub fn watch_shader(
+        &mut self,
+        device: &Device,
+        path: impl Into<PathBuf>,
+    ) -> Result<ShaderHandle, ReloadError> {
+        let path = path.into();
+        let modified = modification_time(&path)?;
+        let module = load_shader(device, &path)?;
+        self.shaders.push(WatchedShader {
+            path,
+            modified: Some(modified),
+            module,
+        });
+        Ok(ShaderHandle(self.shaders.len() - 1))
+    }
+
+    /// Returns the current module of a watched shader.
+    pub fn shader(&self, handle: ShaderHandle) -> &ShaderModule {
+        &self.shaders[handle.0].module
+    }
+
+    /// Registers a render pipeline created by `build` from the modules of `shaders`.
+    ///
+    /// `build` is called immediately, and again every time one of `shaders` is reloaded.
+    /// The modules are passed in the same order as `shaders`.
+    pub fn add_render_pipeline(
+        &mut self,
+        label: impl Into<String>,
+        shaders: &[ShaderHandle],
+        build: impl Fn(&Device, &[&ShaderModule]) -> RenderPipeline + 'static,
+        device: &Device,
+    ) -> RenderPipelineHandle {
+        let managed = self.manage(label.into(), shaders, Box::new(build), device);
+        self.render_pipelines.push(managed);
+        RenderPipelineHandle(self.render_pipelines.len() - 1)
+    }
+
+    /// Registers a compute pipeline created by `build` from the modules of `shaders`.
+    ///
+    /// `build` is called immediately, and again every time one of `shaders` is reloaded.
+    /// The modules are passed in the same order as `shaders`.
+    pub fn add_compute_pipeline(
+        &mut self,
+        label: impl Into<String>,
+        shaders: &[ShaderHandle],
+        build: impl Fn(&Device, &[&ShaderModule]) -> ComputePipeline + 'static,
+        device: &Device,
+    ) -> ComputePipelineHandle {
+        let managed = self.manage(label.into(), shaders, Box::new(build), device);
+        self.compute_pipelines.push(managed);
+        ComputePipelineHandle(self.compute_pipelines.len() - 1)
+    }
+
+    /// Returns the current version of a render pipeline.
+    pub fn render_pipeline(&self, handle: RenderPipelineHandle) -> &RenderPipeline {
+        &self.render_pipelines[handle.0].pipeline
+    }
+
+    /// Returns the current version of a compute pipeline.
+    pub fn compute_pipeline(&self, handle: ComputePipelineHandle) -> &ComputePipeline {
+        &self.compute_pipelines[handle.0].pipeline
+    }
+
+    /// Reloads all shaders modified since the last call, and rebuilds the pipelines using them.
+    ///
+    /// Returns every error encountered on the way. Objects that failed to reload are left untouched,
+    /// and shaders which failed are tried again by the next call.
+    pub async fn update(&mut self, device: &Device) -> Vec<ReloadError> {
+        let mut errors = Vec::new();
+        let mut changed = Vec::new();
+
+        for (index, shader) in self.shaders.iter_mut().enumerate() {
+            let modified = match modification_time(&shader.path) {
+                Ok(modified) => modified,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+            if shader.modified == Some(modified) {
+                continue;
+            }
+
+            // The modification time is only recorded once the shader loads, so that failures
+            // are retried by the next update even if the file doesn't change again
+            device.push_error_scope(ErrorFilter::Validation);
+            let module = load_shader(device, &shader.path);
+            let scope_error = device.pop_error_scope().await;
+            match (module, scope_error) {
+                (Ok(module), None) => {
+                    shader.module = module;
+                    shader.modified = Some(modified);
+                    changed.push(ShaderHandle(index));
+                }
+                (Ok(_), Some(error)) => errors.push(ReloadError::Shader {
+                    path: shader.path.clone(),
+                    error,
+                }),
+                (Err(error), _) => errors.push(error),
+            }
+        }
+
+        if changed.is_empty() {
+            return errors;
+        }
+
+        let shaders = &self.shaders;
+        for managed in self.render_pipelines.iter_mut() {
+            if let Err(error) = managed.rebuild(device, shaders, &changed).await {
+                errors.push(error);
+            }
+        }
+        for managed in self.compute_pipelines.iter_mut() {
+            if let Err(error) = managed.rebuild(device, shaders, &changed).await {
+                errors.push(error);
+            }
+        }
+        errors
+    }
+
+    fn manage<P>(
+        &self,
+        label: String,
+        shaders: &[ShaderHandle],
+        build: Box<dyn Fn(&Device, &[&ShaderModule]) -> P>,
+        device: &Device,
+    ) -> Managed<P> {
+        let modules = shaders
+            .iter()
+            .map(|handle| &self.shaders[handle.0].module)
+            .collect::<Vec<_>>();
+        let pipeline = build(device, &modules);
+        Managed {
+            label,
+            shaders: shaders.to_vec(),
+            build,
+            pipeline,
+        }
+    }
+}
+
+impl<P> Managed<P> {
+    async fn rebuild(
+        &mut self,
+        device: &Device,
+        shaders: &[WatchedShader],
+        changed: &[ShaderHandle],
+    ) -> Result<(), ReloadError> {
+        if !self.shaders.iter().any(|handle| changed.contains(handle)) {
+            return Ok(());
+        }
+        let modules = self
+            .shaders
+            .iter()
+            .map(|handle| &shaders[handle.0].module)
+            .collect::<Vec<_>>();
+
+        device.push_error_scope(ErrorFilter::Validation);
+        let pipeline = (self.build)(device, &modules);
+        match device.pop_error_scope().await {
+            None => {
+                self.pipeline = pipeline;
+                Ok(())
+            }
+            Some(error) => Err(ReloadError::Pipeline {
+                label: self.label.clone(),
+                error,
+            }),
+        }
+    }
+}
+
+fn modification_time(path: &Path) -> Result<SystemTime, ReloadError> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|source| ReloadError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Read the shader at `path`, as SPIR-V if its extension is `.spv` and as WGSL otherwise.
+fn read_shader_source(path: &Path) -> Result<ShaderSource<'static>, ReloadError> {
+    let io_error = |source| ReloadError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if path.extension().map_or(false, |ext| ext == "spv") {
+        let data = fs::read(path).map_err(io_error)?;
+        let source = super::try_make_spirv(&data).map_err(|source| ReloadError::Spirv {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(match source {
+            ShaderSource::SpirV(words) => ShaderSource::SpirV(Cow::Owned(words.into_owned())),
+            other => other,
+        })
+    } else {
+        Ok(ShaderSource::Wgsl(Cow::Owned(
+            fs::read_to_string(path).map_err(io_error)?,
+        )))
+    }
+}
+
+fn load_shader(device: &Device, path: &Path) -> Result<ShaderModule, ReloadError> {
+    let source = read_shader_source(path)?;
+    let label = path.to_string_lossy();
+    Ok(device.create_shader_module(&ShaderModuleDescriptor {
+        label: Some(&label),
+        source,
+        flags: ShaderFlags::VALIDATION,
+    }))
+}
+
+#[test]
+fn shader_sources() {
+    let dir = std::env::temp_dir().join(format!("wgpu-hot-reload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let wgsl = dir.join("shader.wgsl");
+    fs::write(&wgsl, "fn main() {}").unwrap();
+    assert!(matches!(
+        read_shader_source(&wgsl),
+        Ok(ShaderSource::Wgsl(ref code)) if code == "fn main() {}"
+    ));
+
+    let spirv = dir.join("shader.spv");
+    let words = [0x0723_0203u32, 0x0001_0000, 0, 1, 0];
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .collect::<Vec<_>>();
+    fs::write(&spirv, &bytes).unwrap();
+    assert!(matches!(
+        read_shader_source(&spirv),
+        Ok(ShaderSource::SpirV(ref code)) if code[..] == words[..]
+    ));
+    fs::write(&spirv, &bytes[..6]).unwrap();
+    assert!(matches!(
+        read_shader_source(&spirv),
+        Err(ReloadError::Spirv { .. })
+    ));
+
+    let missing = dir.join("missing.wgsl");
+    assert!(matches!(
+        read_shader_source(&missing),
+        Err(ReloadError::Io { ref path, .. }) if *path == missing
+    ));
+    assert!(matches!(
+        modification_time(&missing),
+        Err(ReloadError::Io { .. })
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,164 @@
+use std::{mem, slice};
+
+use super::{BufferInitDescriptor, DeviceExt};
+use crate::{Buffer, BufferAddress, BufferUsage, Device};
+
+/// Arguments of an indirect draw, as read by [`RenderPass::draw_indirect`].
+///
+/// [`RenderPass::draw_indirect`]: crate::RenderPass::draw_indirect
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DrawIndirectArgs {
+    /// The number of vertices to draw.
+    pub vertex_count: u32,
+    /// The number of instances to draw.
+    pub instance_count: u32,
+    /// The index of the first vertex to draw.
+    pub first_vertex: u32,
+    /// The instance ID of the first instance to draw.
+    pub first_instance: u32,
+}
+
+/// Arguments of an indexed indirect draw, as read by [`RenderPass::draw_indexed_indirect`].
+///
+/// [`RenderPass::draw_indexed_indirect`]: crate::RenderPass::draw_indexed_indirect
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DrawIndexedIndirectArgs {
+    /// The number of indices to draw.
+    pub index_count: u32,
+    /// The number of instances to draw.
+    pub instance_count: u32,
+    /// The first index within the index buffer.
+    pub first_index: u32,
+    /// The value added to the vertex index before indexing into the vertex buffer.
+    pub base_vertex: i32,
+    /// The instance ID of the first instance to draw.
+    pub first_instance: u32,
+}
+
+/// Arguments of an indirect dispatch, as read by [`ComputePass::dispatch_indirect`].
+///
+/// [`ComputePass::dispatch_indirect`]: crate::ComputePass::dispatch_indirect
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DispatchIndirectArgs {
+    /// The number of work groups in the X dimension.
+    pub x: u32,
+    /// The number of work groups in the Y dimension.
+    pub y: u32,
+    /// The number of work groups in the Z dimension.
+    pub z: u32,
+}
+
+macro_rules! impl_as_bytes {
+    ($($ty:ty),*) => {$(
+        impl $ty {
+            /// Size of the arguments in an indirect buffer.
+            pub const SIZE: BufferAddress = mem::size_of::<Self>() as BufferAddress;
+
+            /// The arguments as they are laid out in an indirect buffer.
+            pub fn as_bytes(&self) -> &[u8] {
+                // The type is `repr(C)` and made of 4 byte integers, so it has no padding
+                unsafe { slice::from_raw_parts(self as *const Self as *const u8, mem::size_of::<Self>()) }
+            }
+        }
+    )*};
+}
+
+impl_as_bytes!(
+    DrawIndirectArgs,
+    DrawIndexedIndirectArgs,
+    DispatchIndirectArgs
+);
+
+/// Accumulates indirect arguments, to be uploaded into a single buffer.
+///
+/// Each `push` method returns the offset of the arguments, to be passed to the indirect draw
+/// or dispatch reading them. The arguments of the different kinds can be mixed.
+#[derive(Clone, Debug, Default)]
+pub struct IndirectBufferBuilder {
+    data: Vec<u8>,
+}
+
+impl IndirectBufferBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> BufferAddress {
+        let offset = self.data.len() as BufferAddress;
+        self.data.extend_from_slice(bytes);
+        offset
+    }
+
+    /// Add the arguments of a draw, returning their offset.
+    pub fn push_draw(&mut self, args: DrawIndirectArgs) -> BufferAddress {
+        self.push(args.as_bytes())
+    }
+
+    /// Add the arguments of an indexed draw, returning their offset.
+    pub fn push_draw_indexed(&mut self, args: DrawIndexedIndirectArgs) -> BufferAddress {
+        self.push(args.as_bytes())
+    }
+
+    /// Add the arguments of a dispatch, returning their offset.
+    pub fn push_dispatch(&mut self, args: DispatchIndirectArgs) -> BufferAddress {
+        self.push(args.as_bytes())
+    }
+
+    /// Size of the accumulated arguments, in bytes.
+    pub fn size(&self) -> BufferAddress {
+        self.data.len() as BufferAddress
+    }
+
+    /// Returns `true` if no arguments were added.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Remove all the arguments.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// The accumulated arguments, as they are laid out in the buffer.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Create a buffer holding the arguments, usable as [`BufferUsage::INDIRECT`] and
+    /// [`BufferUsage::STORAGE`] so that compute shaders can update it.
+    pub fn build(&self, device: &Device, label: Option<&str>) -> Buffer {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label,
+            contents: &self.data,
+            usage: BufferUsage::INDIRECT | BufferUsage::STORAGE,
+        })
+    }
+}
+
+#[test]
+fn indirect_args_layout() {
+    assert_eq!(DrawIndirectArgs::SIZE, 16);
+    assert_eq!(DrawIndexedIndirectArgs::SIZE, 20);
+    assert_eq!(DispatchIndirectArgs::SIZE, 12);
+
+    let mut builder = IndirectBufferBuilder::new();
+    let draw = builder.push_draw(DrawIndirectArgs {
+        vertex_count: 3,
+        instance_count: 1,
+        ..Default::default()
+    });
+    let indexed = builder.push_draw_indexed(DrawIndexedIndirectArgs {
+        base_vertex: -1,
+        ..Default::default()
+    });
+    let dispatch = builder.push_dispatch(DispatchIndirectArgs { x: 2, y: 1, z: 1 });
+    assert_eq!((draw, indexed, dispatch), (0, 16, 36));
+    assert_eq!(builder.size(), 48);
+    assert_eq!(&builder.as_bytes()[..4], &3u32.to_ne_bytes());
+    assert_eq!(&builder.as_bytes()[28..32], &(-1i32).to_ne_bytes());
+    assert_eq!(&builder.as_bytes()[36..40], &2u32.to_ne_bytes());
+}
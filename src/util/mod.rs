@@ -1,59 +1,88 @@
 //! Utility structures and functions.
 
 mod belt;
+mod bind_group;
+mod cache;
+mod composer;
 mod device;
 mod encoder;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod indirect;
+mod multi_draw;
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel;
+mod pipeline;
+mod pipeline_cache;
+mod pipeline_statistics;
+mod profiler;
+mod render_graph;
+mod resource_pool;
+mod resource_table;
+mod spirv;
+mod vertex;
 
 use std::{
-    borrow::Cow,
     future::Future,
-    mem::{align_of, size_of},
-    ptr::copy_nonoverlapping,
+    pin::Pin,
+    ptr,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
 };
 
 pub use belt::StagingBelt;
+pub use bind_group::{BindGroupBuilder, BindGroupBuilderError};
+pub use cache::{
+    BindGroupCache, BindGroupCacheEntry, CachedBindingResource, SamplerCache, SamplerKey,
+};
+pub use composer::{ComposeError, ComposedShader, ShaderComposer, SourceLocation};
 pub use device::{BufferInitDescriptor, DeviceExt};
 pub use encoder::RenderEncoder;
+#[cfg(not(target_arch = "wasm32"))]
+pub use hot_reload::{
+    ComputePipelineHandle, HotReload, ReloadError, RenderPipelineHandle, ShaderHandle,
+};
+pub use indirect::{
+    DispatchIndirectArgs, DrawIndexedIndirectArgs, DrawIndirectArgs, IndirectBufferBuilder,
+};
+pub use multi_draw::{IndirectCountDraws, MultiDrawIndirect};
+#[cfg(not(target_arch = "wasm32"))]
+pub use parallel::ParallelRecorder;
+pub use pipeline::{BlendMode, RenderPipelineBuilder, RenderPipelineBuilderError};
+pub use pipeline_cache::{PipelineCache, PipelineCacheError, PipelineKey};
+pub use pipeline_statistics::{PipelineStatistics, PipelineStatisticsQueries};
+pub use profiler::{GpuProfiler, GpuTimerScope, TimestampEncoder};
+pub use render_graph::{
+    BufferHandle, GraphResources, PassBuilder, PassId, RenderGraph, RenderGraphError,
+    RenderGraphPlan, TextureHandle,
+};
+pub use resource_pool::{ResourcePool, ResourcePoolError};
+pub use resource_table::{ResourceTable, ResourceTableDescriptor};
+pub use spirv::{make_spirv, try_make_spirv, SpirvError, SpirvHeader};
+pub use vertex::VertexLayout;
+pub use wgpu_derive::VertexLayout;
 
-/// Treat the given byte slice as a SPIR-V module.
-///
-/// # Panic
-///
-/// This function panics if:
-///
-/// - Input length isn't multiple of 4
-/// - Input is longer than [`usize::max_value`]
-/// - SPIR-V magic number is missing from beginning of stream
-pub fn make_spirv<'a>(data: &'a [u8]) -> super::ShaderSource<'a> {
-    const MAGIC_NUMBER: u32 = 0x0723_0203;
-
-    assert_eq!(
-        data.len() % size_of::<u32>(),
-        0,
-        "data size is not a multiple of 4"
-    );
+/// Future of a buffer mapping, kept by the utilities until it completes.
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), super::BufferAsyncError>> + Send>>;
 
-    //If the data happens to be aligned, directly use the byte array,
-    // otherwise copy the byte array in an owned vector and use that instead.
-    let words = if data.as_ptr().align_offset(align_of::<u32>()) == 0 {
-        let (pre, words, post) = unsafe { data.align_to::<u32>() };
-        debug_assert!(pre.is_empty());
-        debug_assert!(post.is_empty());
-        Cow::from(words)
-    } else {
-        let mut words = vec![0u32; data.len() / size_of::<u32>()];
-        unsafe {
-            copy_nonoverlapping(data.as_ptr(), words.as_mut_ptr() as *mut u8, data.len());
-        }
-        Cow::from(words)
-    };
+fn noop_raw_waker() -> RawWaker {
+    fn clone(_: *const ()) -> RawWaker {
+        noop_raw_waker()
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    RawWaker::new(ptr::null(), &VTABLE)
+}
 
-    assert_eq!(
-        words[0], MAGIC_NUMBER,
-        "wrong magic word {:x}. Make sure you are using a binary SPIRV file.",
-        words[0]
-    );
-    super::ShaderSource::SpirV(words)
+/// Returns the output of `future` if it is ready, without waiting for it.
+///
+/// Mappings complete when the device is polled, whether their future is polled or not, so
+/// this checks them without an executor.
+fn poll_now<F: Future + Unpin>(future: &mut F) -> Option<F::Output> {
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    match Pin::new(future).poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
 }
 
 /// CPU accessible buffer used to download data back from the GPU.
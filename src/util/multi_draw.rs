@@ -0,0 +1,394 @@
+use std::borrow::Cow;
+
+use parking_lot::Mutex;
+
+use super::{BufferInitDescriptor, DeviceExt, DrawIndexedIndirectArgs, DrawIndirectArgs};
+use crate::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferAddress, BufferBindingType, BufferDescriptor,
+    BufferSize, BufferSlice, BufferUsage, CommandEncoder, ComputePassDescriptor, ComputePipeline,
+    ComputePipelineDescriptor, Device, Features, PipelineLayoutDescriptor, RenderPass, ShaderFlags,
+    ShaderModuleDescriptor, ShaderSource, ShaderStage,
+};
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Copies the first `count` draws to the output, and zeroes the remaining ones up to `max_count`,
+/// which makes them empty draws.
+const COMPACT_SHADER: &str = r#"
+[[block]]
+struct Params {
+    max_count: u32;
+    stride: u32;
+    args_offset: u32;
+    count_offset: u32;
+};
+
+[[block]]
+struct Words {
+    data: [[stride(4)]] array<u32>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: Params;
+[[group(0), binding(1)]] var<storage> args: [[access(read)]] Words;
+[[group(0), binding(2)]] var<storage> count: [[access(read)]] Words;
+[[group(0), binding(3)]] var<storage> draws: [[access(read_write)]] Words;
+
+[[builtin(global_invocation_id)]] var global_id: vec3<u32>;
+
+[[stage(compute), workgroup_size(64)]]
+fn main() {
+    const draw: u32 = global_id.x;
+    if (draw >= params.max_count) {
+        return;
+    }
+    const draw_count: u32 = count.data[params.count_offset];
+    var word: u32 = 0u;
+    loop {
+        if (word >= params.stride) {
+            break;
+        }
+        const index: u32 = draw * params.stride + word;
+        if (draw < draw_count) {
+            draws.data[index] = args.data[params.args_offset + index];
+        } else {
+            draws.data[index] = 0u;
+        }
+        continuing {
+            word = word + 1u;
+        }
+    }
+}
+"#;
+
+/// Draws prepared by [`MultiDrawIndirect::prepare_indirect_count`] or
+/// [`MultiDrawIndirect::prepare_indexed_indirect_count`].
+#[derive(Debug)]
+pub struct IndirectCountDraws<'a> {
+    inner: CountDraws<'a>,
+    indexed: bool,
+    max_count: u32,
+}
+
+#[derive(Debug)]
+enum CountDraws<'a> {
+    Native {
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count_buffer: &'a Buffer,
+        count_offset: BufferAddress,
+    },
+    Emulated {
+        draws: Buffer,
+    },
+}
+
+#[derive(Debug)]
+struct Compactor {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+/// Multi-draw-indirect with a fallback for devices missing the features.
+///
+/// Without [`Features::MULTI_DRAW_INDIRECT`], multi-draws are issued as a loop of
+/// `draw_indirect` calls. Without [`Features::MULTI_DRAW_INDIRECT_COUNT`], the count variants
+/// are emulated in two steps:
+/// - before the render pass, a compute pass copies the arguments of the first `count` draws,
+///   and zeroes the following ones up to `max_count`, so that they draw nothing,
+/// - in the render pass, `max_count` draws are issued from the copied arguments.
+///
+/// When emulated, the indirect and count buffers are read by a compute shader, so they need
+/// [`BufferUsage::STORAGE`], and their offsets must be multiples of 4. The compute pipeline is
+/// created when the first count variant is prepared.
+///
+/// ```no_run
+/// # fn example(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder,
+/// #     args: &wgpu::Buffer, count: &wgpu::Buffer, desc: &wgpu::RenderPassDescriptor) {
+/// let multi_draw = wgpu::util::MultiDrawIndirect::new(device);
+/// let draws = multi_draw.prepare_indexed_indirect_count(
+///     device,
+///     encoder,
+///     args.slice(..),
+///     count.slice(..),
+///     1024,
+/// );
+/// let mut pass = encoder.begin_render_pass(desc);
+/// multi_draw.draw_indirect_count(&mut pass, &draws);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MultiDrawIndirect {
+    features: Features,
+    compactor: Mutex<Option<Compactor>>,
+}
+
+impl MultiDrawIndirect {
+    /// Create the emulation of the multi-draw features which `device` doesn't support.
+    pub fn new(device: &Device) -> Self {
+        Self {
+            features: device.features(),
+            compactor: Mutex::new(None),
+        }
+    }
+
+    fn create_compactor(device: &Device) -> Compactor {
+        let storage = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStage::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("multi-draw compaction"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(16),
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("multi-draw compaction"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let module = device.create_shader_module(&ShaderModuleDescriptor {
+            label: Some("multi-draw compaction"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(COMPACT_SHADER)),
+            flags: ShaderFlags::VALIDATION,
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("multi-draw compaction"),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: "main",
+            constants: &[],
+        });
+        Compactor { layout, pipeline }
+    }
+
+    /// Issue `count` draws from `indirect_buffer`, with a multi-draw if the device supports it.
+    ///
+    /// See [`RenderPass::multi_draw_indirect`].
+    pub fn draw_indirect<'a>(
+        &self,
+        pass: &mut RenderPass<'a>,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        if self.features.contains(Features::MULTI_DRAW_INDIRECT) {
+            pass.multi_draw_indirect(indirect_buffer, indirect_offset, count);
+        } else {
+            for index in 0..count as BufferAddress {
+                pass.draw_indirect(
+                    indirect_buffer,
+                    indirect_offset + index * DrawIndirectArgs::SIZE,
+                );
+            }
+        }
+    }
+
+    /// Issue `count` indexed draws from `indirect_buffer`, with a multi-draw if the device
+    /// supports it.
+    ///
+    /// See [`RenderPass::multi_draw_indexed_indirect`].
+    pub fn draw_indexed_indirect<'a>(
+        &self,
+        pass: &mut RenderPass<'a>,
+        indirect_buffer: &'a Buffer,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        if self.features.contains(Features::MULTI_DRAW_INDIRECT) {
+            pass.multi_draw_indexed_indirect(indirect_buffer, indirect_offset, count);
+        } else {
+            for index in 0..count as BufferAddress {
+                pass.draw_indexed_indirect(
+                    indirect_buffer,
+                    indirect_offset + index * DrawIndexedIndirectArgs::SIZE,
+                );
+            }
+        }
+    }
+
+    fn prepare<'b>(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        indirect: BufferSlice<'b>,
+        count: BufferSlice<'b>,
+        max_count: u32,
+        indexed: bool,
+    ) -> IndirectCountDraws<'b> {
+        let (indirect_buffer, indirect_offset) = (indirect.buffer, indirect.offset);
+        let (count_buffer, count_offset) = (count.buffer, count.offset);
+        if self.features.contains(Features::MULTI_DRAW_INDIRECT_COUNT) {
+            return IndirectCountDraws {
+                inner: CountDraws::Native {
+                    indirect_buffer,
+                    indirect_offset,
+                    count_buffer,
+                    count_offset,
+                },
+                indexed,
+                max_count,
+            };
+        }
+
+        let stride = if indexed {
+            DrawIndexedIndirectArgs::SIZE
+        } else {
+            DrawIndirectArgs::SIZE
+        };
+        let draws = device.create_buffer(&BufferDescriptor {
+            label: Some("multi-draw arguments"),
+            size: (max_count as BufferAddress * stride).max(stride),
+            usage: BufferUsage::STORAGE | BufferUsage::INDIRECT,
+            mapped_at_creation: false,
+        });
+        if max_count > 0 {
+            let mut compactor = self.compactor.lock();
+            let compactor = compactor.get_or_insert_with(|| Self::create_compactor(device));
+            let params = [
+                max_count,
+                (stride / 4) as u32,
+                (indirect_offset / 4) as u32,
+                (count_offset / 4) as u32,
+            ];
+            let params = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("multi-draw compaction"),
+                contents: &params
+                    .iter()
+                    .flat_map(|word| word.to_ne_bytes().to_vec())
+                    .collect::<Vec<_>>(),
+                usage: BufferUsage::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("multi-draw compaction"),
+                layout: &compactor.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: indirect_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: count_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: draws.as_entire_binding(),
+                    },
+                ],
+            });
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("multi-draw compaction"),
+            });
+            pass.set_pipeline(&compactor.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch((max_count + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE, 1, 1);
+        }
+        IndirectCountDraws {
+            inner: CountDraws::Emulated { draws },
+            indexed,
+            max_count,
+        }
+    }
+
+    /// Prepare at most `max_count` draws from the `indirect` arguments, the actual count being
+    /// read from the start of `count`. This must be recorded before the render pass drawing them.
+    ///
+    /// See [`RenderPass::multi_draw_indirect_count`].
+    pub fn prepare_indirect_count<'b>(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        indirect: BufferSlice<'b>,
+        count: BufferSlice<'b>,
+        max_count: u32,
+    ) -> IndirectCountDraws<'b> {
+        self.prepare(device, encoder, indirect, count, max_count, false)
+    }
+
+    /// Prepare at most `max_count` indexed draws from the `indirect` arguments, the actual
+    /// count being read from the start of `count`. This must be recorded before the render pass
+    /// drawing them.
+    ///
+    /// See [`RenderPass::multi_draw_indexed_indirect_count`].
+    pub fn prepare_indexed_indirect_count<'b>(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        indirect: BufferSlice<'b>,
+        count: BufferSlice<'b>,
+        max_count: u32,
+    ) -> IndirectCountDraws<'b> {
+        self.prepare(device, encoder, indirect, count, max_count, true)
+    }
+
+    /// Issue prepared draws.
+    pub fn draw_indirect_count<'a>(
+        &self,
+        pass: &mut RenderPass<'a>,
+        draws: &'a IndirectCountDraws<'a>,
+    ) {
+        match draws.inner {
+            CountDraws::Native {
+                indirect_buffer,
+                indirect_offset,
+                count_buffer,
+                count_offset,
+            } => {
+                if draws.indexed {
+                    pass.multi_draw_indexed_indirect_count(
+                        indirect_buffer,
+                        indirect_offset,
+                        count_buffer,
+                        count_offset,
+                        draws.max_count,
+                    );
+                } else {
+                    pass.multi_draw_indirect_count(
+                        indirect_buffer,
+                        indirect_offset,
+                        count_buffer,
+                        count_offset,
+                        draws.max_count,
+                    );
+                }
+            }
+            CountDraws::Emulated { draws: ref buffer } => {
+                if draws.indexed {
+                    self.draw_indexed_indirect(pass, buffer, 0, draws.max_count);
+                } else {
+                    self.draw_indirect(pass, buffer, 0, draws.max_count);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn compact_shader_is_valid() {
+    let module = naga::front::wgsl::parse_str(COMPACT_SHADER).unwrap();
+    naga::proc::Validator::new().validate(&module).unwrap();
+}
@@ -0,0 +1,266 @@
+use std::{
+    fmt,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    CommandBuffer, CommandEncoder, CommandEncoderDescriptor, Device, RenderBundle,
+    RenderBundleDescriptor, RenderBundleEncoder, RenderBundleEncoderDescriptor, TextureFormat,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Owned copy of a [`RenderBundleEncoderDescriptor`], to send to the workers.
+struct BundleFormat {
+    label: Option<String>,
+    color_formats: Vec<TextureFormat>,
+    depth_stencil_format: Option<TextureFormat>,
+    sample_count: u32,
+}
+
+/// Pool of threads recording render bundles or command buffers in parallel.
+///
+/// A list of items, typically the objects of a scene, is split into one contiguous chunk per
+/// thread. Each chunk is recorded on a worker, and the results are returned in the order of
+/// the chunks, so that executing the bundles, or submitting the command buffers, in that order
+/// draws the items in their original order.
+///
+/// The items are shared with the workers, so they usually hold the `Arc`s of the pipelines,
+/// bind groups and buffers to draw with.
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # struct Mesh { pipeline: wgpu::RenderPipeline, vertices: wgpu::Buffer, count: u32 }
+/// # fn example(device: Arc<wgpu::Device>, meshes: Arc<[Mesh]>,
+/// #     encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+/// let recorder = wgpu::util::ParallelRecorder::new(4);
+/// let bundles = recorder.record_bundles(
+///     &device,
+///     &wgpu::RenderBundleEncoderDescriptor {
+///         label: Some("scene"),
+///         color_formats: &[wgpu::TextureFormat::Bgra8UnormSrgb],
+///         depth_stencil_format: None,
+///         sample_count: 1,
+///     },
+///     &meshes,
+///     |encoder, meshes| {
+///         for mesh in meshes {
+///             encoder.set_pipeline(&mesh.pipeline);
+///             encoder.set_vertex_buffer(0, mesh.vertices.slice(..));
+///             encoder.draw(0..mesh.count, 0..1);
+///         }
+///     },
+/// );
+///
+/// let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+///     label: Some("scene"),
+///     color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+///         attachment: view,
+///         resolve_target: None,
+///         ops: wgpu::Operations::default(),
+///     }],
+///     depth_stencil_attachment: None,
+/// });
+/// pass.execute_bundles(bundles.iter());
+/// # }
+/// ```
+pub struct ParallelRecorder {
+    sender: Option<mpsc::Sender<Job>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl fmt::Debug for ParallelRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParallelRecorder")
+            .field("threads", &self.threads.len())
+            .finish()
+    }
+}
+
+/// Split `len` items into at most `count` contiguous and non-empty ranges.
+fn split(len: usize, count: usize) -> impl Iterator<Item = Range<usize>> {
+    let chunk_size = ((len + count - 1) / count).max(1);
+    (0..len)
+        .step_by(chunk_size)
+        .map(move |start| start..len.min(start + chunk_size))
+}
+
+impl ParallelRecorder {
+    /// Create a pool of `threads` recording threads.
+    ///
+    /// # Panics
+    ///
+    /// - `threads` is 0.
+    pub fn new(threads: usize) -> Self {
+        assert!(threads > 0, "ParallelRecorder needs at least one thread");
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let threads = (0..threads)
+            .map(|index| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("wgpu recording worker {}", index))
+                    .spawn(move || loop {
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            // A panicking job drops its result sender, which is reported
+                            // by the recording call, so the worker can keep going
+                            Ok(job) => {
+                                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                            }
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("Failed to spawn a recording worker thread")
+            })
+            .collect();
+        Self {
+            sender: Some(sender),
+            threads,
+        }
+    }
+
+    /// Number of recording threads.
+    pub fn thread_count(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Run `job` on each chunk of `items`, and return the results in the order of the chunks.
+    fn run<T, R, F>(&self, items: &Arc<[T]>, job: F) -> Vec<R>
+    where
+        T: Send + Sync + 'static,
+        R: Send + 'static,
+        F: Fn(&[T]) -> R + Send + Sync + 'static,
+    {
+        let job = Arc::new(job);
+        let (result_sender, result_receiver) = mpsc::channel();
+        let mut count = 0;
+        for (index, range) in split(items.len(), self.threads.len()).enumerate() {
+            let items = Arc::clone(items);
+            let job = Arc::clone(&job);
+            let result_sender = result_sender.clone();
+            self.sender
+                .as_ref()
+                .unwrap()
+                .send(Box::new(move || {
+                    let result = job(&items[range]);
+                    // The receiver only goes away if the recording call panicked
+                    let _ = result_sender.send((index, result));
+                }))
+                .unwrap();
+            count += 1;
+        }
+        drop(result_sender);
+
+        let mut results = result_receiver.iter().collect::<Vec<_>>();
+        assert_eq!(results.len(), count, "A recording job panicked");
+        results.sort_by_key(|&(index, _)| index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Record `items` into one render bundle per chunk, in parallel.
+    ///
+    /// `record` is called on a worker thread for each chunk, with a fresh encoder created
+    /// from `desc`. The bundles are labelled after `desc`, and returned in the order of the
+    /// chunks, to be executed in that order with [`RenderPass::execute_bundles`].
+    ///
+    /// # Panics
+    ///
+    /// - `record` panics.
+    ///
+    /// [`RenderPass::execute_bundles`]: crate::RenderPass::execute_bundles
+    pub fn record_bundles<T, F>(
+        &self,
+        device: &Arc<Device>,
+        desc: &RenderBundleEncoderDescriptor,
+        items: &Arc<[T]>,
+        record: F,
+    ) -> Vec<RenderBundle>
+    where
+        T: Send + Sync + 'static,
+        F: for<'a> Fn(&mut RenderBundleEncoder<'a>, &'a [T]) + Send + Sync + 'static,
+    {
+        let device = Arc::clone(device);
+        let format = BundleFormat {
+            label: desc.label.map(str::to_string),
+            color_formats: desc.color_formats.to_vec(),
+            depth_stencil_format: desc.depth_stencil_format,
+            sample_count: desc.sample_count,
+        };
+        self.run(items, move |items| {
+            let label = format.label.as_deref();
+            let mut encoder = device.create_render_bundle_encoder(&RenderBundleEncoderDescriptor {
+                label,
+                color_formats: &format.color_formats,
+                depth_stencil_format: format.depth_stencil_format,
+                sample_count: format.sample_count,
+            });
+            record(&mut encoder, items);
+            encoder.finish(&RenderBundleDescriptor { label })
+        })
+    }
+
+    /// Record `items` into one command buffer per chunk, in parallel.
+    ///
+    /// `record` is called on a worker thread for each chunk, with a fresh command encoder,
+    /// in which it typically begins a render pass loading the attachments. The command buffers
+    /// are returned in the order of the chunks, to be submitted in that order.
+    ///
+    /// # Panics
+    ///
+    /// - `record` panics.
+    pub fn record_command_buffers<T, F>(
+        &self,
+        device: &Arc<Device>,
+        label: Option<&str>,
+        items: &Arc<[T]>,
+        record: F,
+    ) -> Vec<CommandBuffer>
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&mut CommandEncoder, &[T]) + Send + Sync + 'static,
+    {
+        let device = Arc::clone(device);
+        let label = label.map(str::to_string);
+        self.run(items, move |items| {
+            let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: label.as_deref(),
+            });
+            record(&mut encoder, items);
+            encoder.finish()
+        })
+    }
+}
+
+impl Drop for ParallelRecorder {
+    fn drop(&mut self) {
+        // The workers stop once the sender is dropped
+        self.sender = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[test]
+fn split_covers_items_in_order() {
+    assert_eq!(split(10, 4).collect::<Vec<_>>(), [0..3, 3..6, 6..9, 9..10]);
+    assert_eq!(split(2, 4).collect::<Vec<_>>(), [0..1, 1..2]);
+    assert_eq!(split(0, 4).count(), 0);
+}
+
+#[test]
+fn recording_types_are_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<CommandEncoder>();
+    assert_send::<CommandBuffer>();
+    assert_send::<crate::RenderPass>();
+    assert_send::<crate::ComputePass>();
+    assert_send::<crate::OwnedRenderPass>();
+    assert_send::<crate::OwnedComputePass>();
+    assert_send::<RenderBundleEncoder>();
+    assert_send::<RenderBundle>();
+}
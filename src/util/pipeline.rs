@@ -0,0 +1,532 @@
+use std::{error, fmt};
+
+use crate::{
+    BindGroupLayout, BlendComponent, BlendFactor, BlendOperation, BlendState, ColorTargetState,
+    ColorWrite, CompareFunction, DepthBiasState, DepthStencilState, Device, Face, FragmentState,
+    FrontFace, IndexFormat, MultisampleState, PipelineConstantId, PipelineLayout,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology, PushConstantRange,
+    RenderPipeline, RenderPipelineDescriptor, ShaderModule, StencilState, TextureFormat,
+    VertexBufferLayout, VertexState,
+};
+
+/// Common ways of blending a color target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Overwrite the target, no blending.
+    Replace,
+    /// Standard alpha blending, with non-premultiplied source colors.
+    Alpha,
+    /// Alpha blending with premultiplied source colors.
+    PremultipliedAlpha,
+    /// Add the source to the target.
+    Additive,
+    /// Multiply the target by the source.
+    Multiply,
+}
+
+impl BlendMode {
+    /// Blend state implementing this mode.
+    pub fn state(self) -> Option<BlendState> {
+        let color = |src_factor, dst_factor| BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: BlendOperation::Add,
+        };
+        let (color, alpha) = match self {
+            Self::Replace => return None,
+            Self::Alpha => (
+                color(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+                color(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ),
+            Self::PremultipliedAlpha => (
+                color(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+                color(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ),
+            Self::Additive => (
+                color(BlendFactor::One, BlendFactor::One),
+                color(BlendFactor::One, BlendFactor::One),
+            ),
+            Self::Multiply => (
+                color(BlendFactor::DstColor, BlendFactor::Zero),
+                color(BlendFactor::DstAlpha, BlendFactor::Zero),
+            ),
+        };
+        Some(BlendState { color, alpha })
+    }
+}
+
+/// Inconsistency detected by [`RenderPipelineBuilder`] before creating the pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderPipelineBuilderError {
+    /// Color targets are set, but there is no fragment stage to write them.
+    MissingFragmentStage,
+    /// The sample count isn't a power of two between 1 and 32.
+    InvalidSampleCount(u32),
+    /// Alpha to coverage is enabled on a single-sampled pipeline.
+    AlphaToCoverageWithoutMultisampling,
+    /// The depth-stencil format isn't a depth format.
+    InvalidDepthFormat(TextureFormat),
+}
+
+impl fmt::Display for RenderPipelineBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFragmentStage => write!(f, "color targets require a fragment stage"),
+            Self::InvalidSampleCount(count) => write!(f, "invalid sample count {}", count),
+            Self::AlphaToCoverageWithoutMultisampling => {
+                write!(f, "alpha to coverage requires a sample count above 1")
+            }
+            Self::InvalidDepthFormat(format) => {
+                write!(f, "{:?} is not a depth format", format)
+            }
+        }
+    }
+}
+
+impl error::Error for RenderPipelineBuilderError {}
+
+/// Depth-stencil state testing and writing the depth, without stencil or depth bias.
+fn depth_state(format: TextureFormat) -> DepthStencilState {
+    DepthStencilState {
+        format,
+        depth_write_enabled: true,
+        depth_compare: CompareFunction::Less,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+        clamp_depth: false,
+    }
+}
+
+/// Fixed-function state of a render pipeline, starting from the WebGPU defaults.
+#[derive(Clone, Debug, Default)]
+struct RenderState {
+    targets: Vec<ColorTargetState>,
+    primitive: PrimitiveState,
+    depth_stencil: Option<DepthStencilState>,
+    multisample: MultisampleState,
+}
+
+impl RenderState {
+    /// The depth-stencil state, which adjusting the depth test requires.
+    fn depth_stencil_mut(&mut self, method: &str) -> &mut DepthStencilState {
+        match self.depth_stencil {
+            Some(ref mut depth_stencil) => depth_stencil,
+            None => panic!(
+                "RenderPipelineBuilder::{} requires a depth-stencil state, set one with `depth` first",
+                method
+            ),
+        }
+    }
+}
+
+/// Check the fixed-function state of a pipeline, with or without a fragment stage.
+fn validate(
+    has_fragment: bool,
+    targets: &[ColorTargetState],
+    multisample: &MultisampleState,
+    depth_stencil: Option<&DepthStencilState>,
+) -> Result<(), RenderPipelineBuilderError> {
+    if !has_fragment && !targets.is_empty() {
+        return Err(RenderPipelineBuilderError::MissingFragmentStage);
+    }
+    let count = multisample.count;
+    if !count.is_power_of_two() || count > 32 {
+        return Err(RenderPipelineBuilderError::InvalidSampleCount(count));
+    }
+    if count == 1 && multisample.alpha_to_coverage_enabled {
+        return Err(RenderPipelineBuilderError::AlphaToCoverageWithoutMultisampling);
+    }
+    if let Some(depth_stencil) = depth_stencil {
+        match depth_stencil.format {
+            TextureFormat::Depth32Float
+            | TextureFormat::Depth24Plus
+            | TextureFormat::Depth24PlusStencil8 => {}
+            format => return Err(RenderPipelineBuilderError::InvalidDepthFormat(format)),
+        }
+    }
+    Ok(())
+}
+
+/// Builder for a [`RenderPipeline`], with defaults matching WebGPU.
+///
+/// Unless changed, the primitive state is a front-facing counter-clockwise triangle list
+/// without culling, there is no depth-stencil state, and the pipeline is single-sampled.
+///
+/// ```no_run
+/// # fn example(device: &wgpu::Device, shader: &wgpu::ShaderModule, layout: &wgpu::BindGroupLayout) {
+/// use wgpu::util::{BlendMode, RenderPipelineBuilder};
+///
+/// let pipeline = RenderPipelineBuilder::new(shader, "vs_main")
+///     .label("sprites")
+///     .bind_group_layout(layout)
+///     .fragment(shader, "fs_main")
+///     .color_target_blended(wgpu::TextureFormat::Bgra8UnormSrgb, BlendMode::Alpha)
+///     .depth(wgpu::TextureFormat::Depth32Float)
+///     .build(device)
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderPipelineBuilder<'a> {
+    label: Option<&'a str>,
+    layout: Option<&'a PipelineLayout>,
+    bind_group_layouts: Vec<&'a BindGroupLayout>,
+    push_constant_ranges: Vec<PushConstantRange>,
+    vertex_module: &'a ShaderModule,
+    vertex_entry_point: &'a str,
+    vertex_constants: &'a [(PipelineConstantId<'a>, f64)],
+    buffers: Vec<VertexBufferLayout<'a>>,
+    fragment: Option<(&'a ShaderModule, &'a str)>,
+    fragment_constants: &'a [(PipelineConstantId<'a>, f64)],
+    state: RenderState,
+}
+
+impl<'a> RenderPipelineBuilder<'a> {
+    /// Start building a pipeline with the given vertex stage.
+    pub fn new(module: &'a ShaderModule, entry_point: &'a str) -> Self {
+        Self {
+            label: None,
+            layout: None,
+            bind_group_layouts: Vec::new(),
+            push_constant_ranges: Vec::new(),
+            vertex_module: module,
+            vertex_entry_point: entry_point,
+            vertex_constants: &[],
+            buffers: Vec::new(),
+            fragment: None,
+            fragment_constants: &[],
+            state: RenderState::default(),
+        }
+    }
+
+    /// Set the debug label of the pipeline.
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Use an existing pipeline layout.
+    ///
+    /// This takes precedence over [`bind_group_layout`](Self::bind_group_layout)
+    /// and [`push_constant_range`](Self::push_constant_range).
+    pub fn layout(mut self, layout: &'a PipelineLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Append a bind group layout to the pipeline layout created by [`build`](Self::build).
+    ///
+    /// Without bind group layouts, push constant ranges or an explicit layout,
+    /// the layout is derived from the shaders.
+    pub fn bind_group_layout(mut self, layout: &'a BindGroupLayout) -> Self {
+        self.bind_group_layouts.push(layout);
+        self
+    }
+
+    /// Append a push constant range to the pipeline layout created by [`build`](Self::build).
+    pub fn push_constant_range(mut self, range: PushConstantRange) -> Self {
+        self.push_constant_ranges.push(range);
+        self
+    }
+
+    /// Set the pipeline-overridable constants of the vertex stage.
+    pub fn vertex_constants(mut self, constants: &'a [(PipelineConstantId<'a>, f64)]) -> Self {
+        self.vertex_constants = constants;
+        self
+    }
+
+    /// Append a vertex buffer layout.
+    pub fn vertex_buffer(mut self, layout: VertexBufferLayout<'a>) -> Self {
+        self.buffers.push(layout);
+        self
+    }
+
+    /// Set the fragment stage.
+    pub fn fragment(mut self, module: &'a ShaderModule, entry_point: &'a str) -> Self {
+        self.fragment = Some((module, entry_point));
+        self
+    }
+
+    /// Set the pipeline-overridable constants of the fragment stage.
+    pub fn fragment_constants(mut self, constants: &'a [(PipelineConstantId<'a>, f64)]) -> Self {
+        self.fragment_constants = constants;
+        self
+    }
+
+    /// Append a color target without blending, writing all channels.
+    pub fn color_target(self, format: TextureFormat) -> Self {
+        self.color_target_blended(format, BlendMode::Replace)
+    }
+
+    /// Append a color target blended with the given mode, writing all channels.
+    pub fn color_target_blended(self, format: TextureFormat, blend: BlendMode) -> Self {
+        self.color_target_state(ColorTargetState {
+            format,
+            blend: blend.state(),
+            write_mask: ColorWrite::ALL,
+        })
+    }
+
+    /// Append a fully described color target.
+    pub fn color_target_state(mut self, state: ColorTargetState) -> Self {
+        self.state.targets.push(state);
+        self
+    }
+
+    /// Set the whole primitive state.
+    pub fn primitive(mut self, primitive: PrimitiveState) -> Self {
+        self.state.primitive = primitive;
+        self
+    }
+
+    /// Set the primitive topology.
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.state.primitive.topology = topology;
+        self
+    }
+
+    /// Set the index format of strip topologies.
+    pub fn strip_index_format(mut self, format: IndexFormat) -> Self {
+        self.state.primitive.strip_index_format = Some(format);
+        self
+    }
+
+    /// Set the winding order of front faces.
+    pub fn front_face(mut self, front_face: FrontFace) -> Self {
+        self.state.primitive.front_face = front_face;
+        self
+    }
+
+    /// Set which faces are culled.
+    pub fn cull_mode(mut self, cull_mode: Option<Face>) -> Self {
+        self.state.primitive.cull_mode = cull_mode;
+        self
+    }
+
+    /// Set the polygon rasterization mode.
+    ///
+    /// Anything but `Fill` requires [`Features::NON_FILL_POLYGON_MODE`](crate::Features::NON_FILL_POLYGON_MODE).
+    pub fn polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.state.primitive.polygon_mode = polygon_mode;
+        self
+    }
+
+    /// Enable depth testing against a target of the given format.
+    ///
+    /// Nearer fragments pass (`CompareFunction::Less`) and write their depth.
+    /// Stencil and depth bias are disabled.
+    pub fn depth(self, format: TextureFormat) -> Self {
+        self.depth_stencil(depth_state(format))
+    }
+
+    /// Enable depth testing against a `Depth32Float` target, see [`depth`](Self::depth).
+    pub fn depth32(self) -> Self {
+        self.depth(TextureFormat::Depth32Float)
+    }
+
+    /// Enable depth testing against a `Depth24PlusStencil8` target, see [`depth`](Self::depth).
+    pub fn depth24_stencil8(self) -> Self {
+        self.depth(TextureFormat::Depth24PlusStencil8)
+    }
+
+    /// Set the comparison of the depth test.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no depth-stencil state yet, see [`depth`](Self::depth).
+    pub fn depth_compare(mut self, compare: CompareFunction) -> Self {
+        self.state.depth_stencil_mut("depth_compare").depth_compare = compare;
+        self
+    }
+
+    /// Set whether the depth is written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no depth-stencil state yet, see [`depth`](Self::depth).
+    pub fn depth_write(mut self, enabled: bool) -> Self {
+        self.state
+            .depth_stencil_mut("depth_write")
+            .depth_write_enabled = enabled;
+        self
+    }
+
+    /// Set the whole depth-stencil state.
+    pub fn depth_stencil(mut self, depth_stencil: DepthStencilState) -> Self {
+        self.state.depth_stencil = Some(depth_stencil);
+        self
+    }
+
+    /// Set the number of samples per pixel.
+    pub fn multisample(mut self, count: u32) -> Self {
+        self.state.multisample.count = count;
+        self
+    }
+
+    /// Set the whole multisample state.
+    pub fn multisample_state(mut self, multisample: MultisampleState) -> Self {
+        self.state.multisample = multisample;
+        self
+    }
+
+    /// Check the pipeline for inconsistencies the builder can detect.
+    ///
+    /// A fragment stage without color targets is valid, e.g. for depth-only passes
+    /// discarding fragments.
+    pub fn validate(&self) -> Result<(), RenderPipelineBuilderError> {
+        validate(
+            self.fragment.is_some(),
+            &self.state.targets,
+            &self.state.multisample,
+            self.state.depth_stencil.as_ref(),
+        )
+    }
+
+    /// Validate and create the pipeline, along with its layout if needed.
+    pub fn build(&self, device: &Device) -> Result<RenderPipeline, RenderPipelineBuilderError> {
+        self.validate()?;
+
+        let created_layout = match self.layout {
+            None if !self.bind_group_layouts.is_empty()
+                || !self.push_constant_ranges.is_empty() =>
+            {
+                Some(device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: self.label,
+                    bind_group_layouts: &self.bind_group_layouts,
+                    push_constant_ranges: &self.push_constant_ranges,
+                }))
+            }
+            _ => None,
+        };
+
+        Ok(device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: self.label,
+            layout: self.layout.or_else(|| created_layout.as_ref()),
+            vertex: VertexState {
+                module: self.vertex_module,
+                entry_point: self.vertex_entry_point,
+                constants: self.vertex_constants,
+                buffers: &self.buffers,
+            },
+            primitive: self.state.primitive.clone(),
+            depth_stencil: self.state.depth_stencil.clone(),
+            multisample: self.state.multisample.clone(),
+            fragment: self.fragment.map(|(module, entry_point)| FragmentState {
+                module,
+                entry_point,
+                constants: self.fragment_constants,
+                targets: &self.state.targets,
+            }),
+        }))
+    }
+}
+
+#[test]
+fn blend_modes() {
+    assert_eq!(BlendMode::Replace.state(), None);
+    let alpha = BlendMode::Alpha.state().unwrap();
+    assert_eq!(alpha.color.src_factor, BlendFactor::SrcAlpha);
+    assert_eq!(alpha.alpha.src_factor, BlendFactor::One);
+    let premultiplied = BlendMode::PremultipliedAlpha.state().unwrap();
+    assert_eq!(premultiplied.color, premultiplied.alpha);
+    assert_eq!(
+        premultiplied.color.dst_factor,
+        BlendFactor::OneMinusSrcAlpha
+    );
+    let additive = BlendMode::Additive.state().unwrap();
+    assert_eq!(
+        (additive.color.src_factor, additive.color.dst_factor),
+        (BlendFactor::One, BlendFactor::One)
+    );
+    let multiply = BlendMode::Multiply.state().unwrap();
+    assert_eq!(multiply.color.src_factor, BlendFactor::DstColor);
+    assert_eq!(multiply.alpha.dst_factor, BlendFactor::Zero);
+}
+
+#[test]
+fn render_pipeline_validation() {
+    let target = ColorTargetState {
+        format: TextureFormat::Rgba8Unorm,
+        blend: None,
+        write_mask: ColorWrite::ALL,
+    };
+    let single = MultisampleState::default();
+    let depth = depth_state(TextureFormat::Depth32Float);
+
+    assert_eq!(
+        validate(true, &[target.clone()], &single, Some(&depth)),
+        Ok(())
+    );
+    // Depth-only passes may still run a fragment shader
+    assert_eq!(validate(true, &[], &single, Some(&depth)), Ok(()));
+    assert_eq!(validate(false, &[], &single, Some(&depth)), Ok(()));
+    assert_eq!(
+        validate(false, &[target.clone()], &single, None),
+        Err(RenderPipelineBuilderError::MissingFragmentStage)
+    );
+    let multisample = |count, alpha_to_coverage_enabled| MultisampleState {
+        count,
+        alpha_to_coverage_enabled,
+        ..MultisampleState::default()
+    };
+    assert_eq!(
+        validate(true, &[], &multisample(3, false), None),
+        Err(RenderPipelineBuilderError::InvalidSampleCount(3))
+    );
+    assert_eq!(
+        validate(true, &[], &multisample(64, false), None),
+        Err(RenderPipelineBuilderError::InvalidSampleCount(64))
+    );
+    assert_eq!(
+        validate(true, &[], &multisample(1, true), None),
+        Err(RenderPipelineBuilderError::AlphaToCoverageWithoutMultisampling)
+    );
+    assert_eq!(validate(true, &[], &multisample(4, true), None), Ok(()));
+    assert_eq!(
+        validate(
+            true,
+            &[],
+            &single,
+            Some(&depth_state(TextureFormat::Rgba8Unorm))
+        ),
+        Err(RenderPipelineBuilderError::InvalidDepthFormat(
+            TextureFormat::Rgba8Unorm
+        ))
+    );
+}
+
+#[test]
+fn render_pipeline_defaults() {
+    // Builders start from the WebGPU defaults
+    let state = RenderState::default();
+    assert!(state.targets.is_empty());
+    assert_eq!(state.primitive.topology, PrimitiveTopology::TriangleList);
+    assert_eq!(state.primitive.front_face, FrontFace::Ccw);
+    assert_eq!(state.primitive.cull_mode, None);
+    assert_eq!(state.depth_stencil, None);
+    assert_eq!(state.multisample.count, 1);
+    assert_eq!(state.multisample.mask, !0);
+
+    let mut state = RenderState {
+        depth_stencil: Some(depth_state(TextureFormat::Depth24PlusStencil8)),
+        ..RenderState::default()
+    };
+    {
+        let depth = state.depth_stencil_mut("depth_compare");
+        assert_eq!(depth.format, TextureFormat::Depth24PlusStencil8);
+        assert!(depth.depth_write_enabled);
+        assert_eq!(depth.depth_compare, CompareFunction::Less);
+        assert_eq!(depth.stencil, StencilState::default());
+        depth.depth_compare = CompareFunction::GreaterEqual;
+    }
+    assert_eq!(
+        state.depth_stencil.map(|depth| depth.depth_compare),
+        Some(CompareFunction::GreaterEqual)
+    );
+}
+
+#[test]
+#[should_panic(expected = "RenderPipelineBuilder::depth_write requires a depth-stencil state")]
+fn depth_write_without_depth() {
+    RenderState::default().depth_stencil_mut("depth_write");
+}
@@ -0,0 +1,643 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error, fmt,
+    io::{self, BufRead},
+    sync::Arc,
+};
+
+use crate::{
+    BlendComponent, ColorTargetState, ComputePipeline, ComputePipelineDescriptor,
+    DepthStencilState, Device, MultisampleState, PipelineConstantId, PipelineLayout,
+    PrimitiveState, RenderPipeline, RenderPipelineDescriptor, ShaderModule, StencilFaceState,
+    VertexBufferLayout,
+};
+
+/// Error returned by [`PipelineCache`] when a descriptor can't be keyed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineCacheError {
+    /// The descriptor uses a shader module which wasn't registered in the cache.
+    UnregisteredShaderModule,
+    /// The descriptor uses a pipeline layout which wasn't registered in the cache.
+    UnregisteredPipelineLayout,
+}
+
+impl fmt::Display for PipelineCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnregisteredShaderModule => {
+                write!(f, "shader module is not registered in the pipeline cache")
+            }
+            Self::UnregisteredPipelineLayout => {
+                write!(f, "pipeline layout is not registered in the pipeline cache")
+            }
+        }
+    }
+}
+
+impl error::Error for PipelineCacheError {}
+
+/// Persistent identifier of a pipeline in a [`PipelineCache`].
+///
+/// It is a FNV-1a hash of the descriptor content, where resources are identified by the names
+/// they were registered with, so it stays the same across runs and builds of an application
+/// using the same version of wgpu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey(pub u64);
+
+impl fmt::Display for PipelineKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+enum CachedPipeline {
+    Render(Arc<RenderPipeline>),
+    Compute(Arc<ComputePipeline>),
+}
+
+/// Names of the registered resources used by a pipeline.
+#[derive(Default)]
+struct Uses {
+    layout: String,
+    modules: Vec<String>,
+}
+
+struct Entry {
+    key: PipelineKey,
+    uses: Uses,
+    pipeline: CachedPipeline,
+}
+
+/// Map evicting its least recently used entry when it's full.
+struct LruMap<V> {
+    capacity: usize,
+    entries: HashMap<Vec<u8>, (V, u64)>,
+    clock: u64,
+}
+
+impl<V> LruMap<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn contains(&self, content: &[u8]) -> bool {
+        self.entries.contains_key(content)
+    }
+
+    /// Return the entry for `content`, marking it as the most recently used.
+    fn get(&mut self, content: &[u8]) -> Option<&V> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(content).map(|(value, last_used)| {
+            *last_used = clock;
+            &*value
+        })
+    }
+
+    fn insert(&mut self, content: Vec<u8>, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&content) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, &(_, last_used))| last_used)
+                .map(|(content, _)| content.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(content, (value, self.clock));
+    }
+
+    /// Remove the entries for which `keep` returns `false`.
+    fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) {
+        self.entries.retain(|_, (value, _)| keep(value));
+    }
+
+    /// The entries, most recently used first.
+    fn values_by_recency(&self) -> Vec<&V> {
+        let mut entries = self.entries.values().collect::<Vec<_>>();
+        entries.sort_by_key(|&&(_, last_used)| std::cmp::Reverse(last_used));
+        entries.into_iter().map(|(value, _)| value).collect()
+    }
+}
+
+/// Cache of render and compute pipelines, deduplicating identical descriptors.
+///
+/// Descriptors are keyed by content, and by the identity of the shader modules and pipeline
+/// layouts they use. These resources must be registered with a name first, and are kept alive
+/// by the cache until they are unregistered or replaced by another resource registered under
+/// the same name, which evicts the pipelines using them. Debug labels are not part of the key. When more than `capacity` pipelines are
+/// cached, the least recently used one is evicted.
+///
+/// The keys of the cached pipelines can be saved with [`save_keys`](Self::save_keys).
+/// At the next start, [`read_keys`](Self::read_keys) loads them back so that a warm-up pass
+/// can pre-create the pipelines which were used, using
+/// [`prewarm_render_pipelines`](Self::prewarm_render_pipelines).
+pub struct PipelineCache {
+    modules: HashMap<usize, (String, Arc<ShaderModule>)>,
+    layouts: HashMap<usize, (String, Arc<PipelineLayout>)>,
+    entries: LruMap<Entry>,
+}
+
+impl fmt::Debug for PipelineCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineCache")
+            .field("capacity", &self.entries.capacity)
+            .field("modules", &self.modules.len())
+            .field("layouts", &self.layouts.len())
+            .field("pipelines", &self.entries.len())
+            .finish()
+    }
+}
+
+fn address<T>(resource: &T) -> usize {
+    resource as *const T as usize
+}
+
+/// 64-bit FNV-1a hash, which is fully specified, unlike the hashers of std.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Serialization of a descriptor, hashed into its [`PipelineKey`].
+///
+/// Every value is written explicitly: integers in little-endian order, strings and lists
+/// prefixed by their length, and enums as their discriminant in wgpu.
+#[derive(Default)]
+struct KeyWriter(Vec<u8>);
+
+impl KeyWriter {
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.0.push(value as u8);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+
+    fn stage(&mut self, module: &str, entry_point: &str, constants: &[(PipelineConstantId, f64)]) {
+        self.str(module);
+        self.str(entry_point);
+        self.u64(constants.len() as u64);
+        for &(id, value) in constants {
+            match id {
+                PipelineConstantId::Name(name) => {
+                    self.u32(0);
+                    self.str(name);
+                }
+                PipelineConstantId::Id(id) => {
+                    self.u32(1);
+                    self.u32(id);
+                }
+            }
+            self.u64(value.to_bits());
+        }
+    }
+
+    fn vertex_buffers(&mut self, buffers: &[VertexBufferLayout]) {
+        self.u64(buffers.len() as u64);
+        for buffer in buffers {
+            self.u64(buffer.array_stride);
+            self.u32(buffer.step_mode as u32);
+            self.u64(buffer.attributes.len() as u64);
+            for attribute in buffer.attributes {
+                self.u32(attribute.format as u32);
+                self.u64(attribute.offset);
+                self.u32(attribute.shader_location);
+            }
+        }
+    }
+
+    fn primitive(&mut self, primitive: &PrimitiveState) {
+        self.u32(primitive.topology as u32);
+        self.option(primitive.strip_index_format, |key, format| {
+            key.u32(format as u32)
+        });
+        self.u32(primitive.front_face as u32);
+        self.option(primitive.cull_mode, |key, face| key.u32(face as u32));
+        self.u32(primitive.polygon_mode as u32);
+    }
+
+    fn stencil_face(&mut self, face: &StencilFaceState) {
+        self.u32(face.compare as u32);
+        self.u32(face.fail_op as u32);
+        self.u32(face.depth_fail_op as u32);
+        self.u32(face.pass_op as u32);
+    }
+
+    fn depth_stencil(&mut self, depth_stencil: &DepthStencilState) {
+        self.u32(depth_stencil.format as u32);
+        self.bool(depth_stencil.depth_write_enabled);
+        self.u32(depth_stencil.depth_compare as u32);
+        self.stencil_face(&depth_stencil.stencil.front);
+        self.stencil_face(&depth_stencil.stencil.back);
+        self.u32(depth_stencil.stencil.read_mask);
+        self.u32(depth_stencil.stencil.write_mask);
+        self.u32(depth_stencil.bias.constant as u32);
+        self.u32(depth_stencil.bias.slope_scale.to_bits());
+        self.u32(depth_stencil.bias.clamp.to_bits());
+        self.bool(depth_stencil.clamp_depth);
+    }
+
+    fn multisample(&mut self, multisample: &MultisampleState) {
+        self.u32(multisample.count);
+        self.u64(multisample.mask);
+        self.bool(multisample.alpha_to_coverage_enabled);
+    }
+
+    fn blend_component(&mut self, component: &BlendComponent) {
+        self.u32(component.src_factor as u32);
+        self.u32(component.dst_factor as u32);
+        self.u32(component.operation as u32);
+    }
+
+    fn color_targets(&mut self, targets: &[ColorTargetState]) {
+        self.u64(targets.len() as u64);
+        for target in targets {
+            self.u32(target.format as u32);
+            self.option(target.blend.as_ref(), |key, blend| {
+                key.blend_component(&blend.color);
+                key.blend_component(&blend.alpha);
+            });
+            self.u32(target.write_mask.bits());
+        }
+    }
+}
+
+impl PipelineCache {
+    /// Create a cache holding at most `capacity` pipelines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            modules: HashMap::new(),
+            layouts: HashMap::new(),
+            entries: LruMap::new(capacity),
+        }
+    }
+
+    /// Register a shader module under `name`.
+    ///
+    /// A module previously registered under `name` is replaced, as when reloading a shader, and
+    /// the pipelines using it are evicted. If `module` was registered under another name, that
+    /// registration is removed as well.
+    ///
+    /// # Panics
+    ///
+    /// - `name` is empty.
+    pub fn register_shader_module(&mut self, name: impl Into<String>, module: Arc<ShaderModule>) {
+        let name = name.into();
+        assert!(!name.is_empty(), "Pipeline cache names must not be empty");
+        if let Some((previous, _)) = self.modules.get(&address(&*module)) {
+            if *previous == name {
+                return;
+            }
+            let previous = previous.clone();
+            self.unregister_shader_module(&previous);
+        }
+        self.unregister_shader_module(&name);
+        self.modules.insert(address(&*module), (name, module));
+    }
+
+    /// Register a pipeline layout under `name`.
+    ///
+    /// A layout previously registered under `name` is replaced, and the pipelines using it are
+    /// evicted. If `layout` was registered under another name, that registration is removed as
+    /// well.
+    ///
+    /// # Panics
+    ///
+    /// - `name` is empty.
+    pub fn register_pipeline_layout(
+        &mut self,
+        name: impl Into<String>,
+        layout: Arc<PipelineLayout>,
+    ) {
+        let name = name.into();
+        assert!(!name.is_empty(), "Pipeline cache names must not be empty");
+        if let Some((previous, _)) = self.layouts.get(&address(&*layout)) {
+            if *previous == name {
+                return;
+            }
+            let previous = previous.clone();
+            self.unregister_pipeline_layout(&previous);
+        }
+        self.unregister_pipeline_layout(&name);
+        self.layouts.insert(address(&*layout), (name, layout));
+    }
+
+    /// Unregister the shader module registered under `name`, and evict the pipelines using it.
+    ///
+    /// Returns the module, if one was registered under `name`.
+    pub fn unregister_shader_module(&mut self, name: &str) -> Option<Arc<ShaderModule>> {
+        let address = self
+            .modules
+            .iter()
+            .find(|(_, (registered, _))| registered == name)
+            .map(|(&address, _)| address)?;
+        self.entries
+            .retain(|entry| !entry.uses.modules.iter().any(|module| module == name));
+        self.modules.remove(&address).map(|(_, module)| module)
+    }
+
+    /// Unregister the pipeline layout registered under `name`, and evict the pipelines using it.
+    ///
+    /// Returns the layout, if one was registered under `name`.
+    pub fn unregister_pipeline_layout(&mut self, name: &str) -> Option<Arc<PipelineLayout>> {
+        let address = self
+            .layouts
+            .iter()
+            .find(|(_, (registered, _))| registered == name)
+            .map(|(&address, _)| address)?;
+        self.entries.retain(|entry| entry.uses.layout != name);
+        self.layouts.remove(&address).map(|(_, layout)| layout)
+    }
+
+    /// Number of cached pipelines.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no pipeline is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn module_name(&self, module: &ShaderModule) -> Result<&str, PipelineCacheError> {
+        self.modules
+            .get(&address(module))
+            .map(|(name, _)| name.as_str())
+            .ok_or(PipelineCacheError::UnregisteredShaderModule)
+    }
+
+    fn layout_name(&self, layout: Option<&PipelineLayout>) -> Result<&str, PipelineCacheError> {
+        match layout {
+            Some(layout) => self
+                .layouts
+                .get(&address(layout))
+                .map(|(name, _)| name.as_str())
+                .ok_or(PipelineCacheError::UnregisteredPipelineLayout),
+            None => Ok(""),
+        }
+    }
+
+    fn render_content(
+        &self,
+        desc: &RenderPipelineDescriptor,
+    ) -> Result<(Vec<u8>, Uses), PipelineCacheError> {
+        let mut key = KeyWriter::default();
+        let mut uses = Uses::default();
+        key.str("render");
+        uses.layout = self.layout_name(desc.layout)?.to_string();
+        key.str(&uses.layout);
+        let vertex_module = self.module_name(desc.vertex.module)?;
+        key.stage(
+            vertex_module,
+            desc.vertex.entry_point,
+            desc.vertex.constants,
+        );
+        uses.modules.push(vertex_module.to_string());
+        key.vertex_buffers(desc.vertex.buffers);
+        key.primitive(&desc.primitive);
+        key.option(desc.depth_stencil.as_ref(), KeyWriter::depth_stencil);
+        key.multisample(&desc.multisample);
+        match desc.fragment {
+            Some(ref fragment) => {
+                key.bool(true);
+                let fragment_module = self.module_name(fragment.module)?;
+                key.stage(fragment_module, fragment.entry_point, fragment.constants);
+                uses.modules.push(fragment_module.to_string());
+                key.color_targets(fragment.targets);
+            }
+            None => key.bool(false),
+        }
+        Ok((key.0, uses))
+    }
+
+    fn compute_content(
+        &self,
+        desc: &ComputePipelineDescriptor,
+    ) -> Result<(Vec<u8>, Uses), PipelineCacheError> {
+        let mut key = KeyWriter::default();
+        let uses = Uses {
+            layout: self.layout_name(desc.layout)?.to_string(),
+            modules: vec![self.module_name(desc.module)?.to_string()],
+        };
+        key.str("compute");
+        key.str(&uses.layout);
+        key.stage(&uses.modules[0], desc.entry_point, desc.constants);
+        Ok((key.0, uses))
+    }
+
+    fn hash(content: &[u8]) -> PipelineKey {
+        PipelineKey(fnv1a(content))
+    }
+
+    /// Persistent key of a render pipeline descriptor.
+    pub fn render_pipeline_key(
+        &self,
+        desc: &RenderPipelineDescriptor,
+    ) -> Result<PipelineKey, PipelineCacheError> {
+        self.render_content(desc)
+            .map(|(content, _)| Self::hash(&content))
+    }
+
+    /// Persistent key of a compute pipeline descriptor.
+    pub fn compute_pipeline_key(
+        &self,
+        desc: &ComputePipelineDescriptor,
+    ) -> Result<PipelineKey, PipelineCacheError> {
+        self.compute_content(desc)
+            .map(|(content, _)| Self::hash(&content))
+    }
+
+    fn insert(&mut self, content: Vec<u8>, uses: Uses, pipeline: CachedPipeline) {
+        let key = Self::hash(&content);
+        self.entries.insert(
+            content,
+            Entry {
+                key,
+                uses,
+                pipeline,
+            },
+        );
+    }
+
+    /// Return the cached pipeline matching `desc`, creating it if needed.
+    pub fn render_pipeline(
+        &mut self,
+        device: &Device,
+        desc: &RenderPipelineDescriptor,
+    ) -> Result<Arc<RenderPipeline>, PipelineCacheError> {
+        let (content, uses) = self.render_content(desc)?;
+        if let Some(Entry {
+            pipeline: CachedPipeline::Render(pipeline),
+            ..
+        }) = self.entries.get(&content)
+        {
+            return Ok(Arc::clone(pipeline));
+        }
+        let pipeline = Arc::new(device.create_render_pipeline(desc));
+        self.insert(content, uses, CachedPipeline::Render(Arc::clone(&pipeline)));
+        Ok(pipeline)
+    }
+
+    /// Return the cached pipeline matching `desc`, creating it if needed.
+    pub fn compute_pipeline(
+        &mut self,
+        device: &Device,
+        desc: &ComputePipelineDescriptor,
+    ) -> Result<Arc<ComputePipeline>, PipelineCacheError> {
+        let (content, uses) = self.compute_content(desc)?;
+        if let Some(Entry {
+            pipeline: CachedPipeline::Compute(pipeline),
+            ..
+        }) = self.entries.get(&content)
+        {
+            return Ok(Arc::clone(pipeline));
+        }
+        let pipeline = Arc::new(device.create_compute_pipeline(desc));
+        self.insert(
+            content,
+            uses,
+            CachedPipeline::Compute(Arc::clone(&pipeline)),
+        );
+        Ok(pipeline)
+    }
+
+    /// Write the keys of the cached pipelines, one per line, most recently used first.
+    pub fn save_keys(&self, mut writer: impl io::Write) -> io::Result<()> {
+        for entry in self.entries.values_by_recency() {
+            writeln!(writer, "{}", entry.key)?;
+        }
+        Ok(())
+    }
+
+    /// Read keys written by [`save_keys`](Self::save_keys). Invalid lines are skipped.
+    pub fn read_keys(reader: impl BufRead) -> io::Result<HashSet<PipelineKey>> {
+        let mut keys = HashSet::new();
+        for line in reader.lines() {
+            if let Ok(key) = u64::from_str_radix(line?.trim(), 16) {
+                keys.insert(PipelineKey(key));
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Create the pipelines among `descs` whose key is in `keys`.
+    ///
+    /// Returns the number of pipelines created.
+    pub fn prewarm_render_pipelines<'a>(
+        &mut self,
+        device: &Device,
+        keys: &HashSet<PipelineKey>,
+        descs: impl IntoIterator<Item = &'a RenderPipelineDescriptor<'a>>,
+    ) -> Result<usize, PipelineCacheError> {
+        let mut count = 0;
+        for desc in descs {
+            let (content, _) = self.render_content(desc)?;
+            if keys.contains(&Self::hash(&content)) && !self.entries.contains(&content) {
+                self.render_pipeline(device, desc)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Create the pipelines among `descs` whose key is in `keys`.
+    ///
+    /// Returns the number of pipelines created.
+    pub fn prewarm_compute_pipelines<'a>(
+        &mut self,
+        device: &Device,
+        keys: &HashSet<PipelineKey>,
+        descs: impl IntoIterator<Item = &'a ComputePipelineDescriptor<'a>>,
+    ) -> Result<usize, PipelineCacheError> {
+        let mut count = 0;
+        for desc in descs {
+            let (content, _) = self.compute_content(desc)?;
+            if keys.contains(&Self::hash(&content)) && !self.entries.contains(&content) {
+                self.compute_pipeline(device, desc)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[test]
+fn key_hash() {
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+
+    let mut key = KeyWriter::default();
+    key.str("compute");
+    key.str("layout");
+    key.stage(
+        "module",
+        "main",
+        &[
+            (PipelineConstantId::Name("scale"), 2.0),
+            (PipelineConstantId::Id(3), -1.0),
+        ],
+    );
+    let hash = PipelineCache::hash(&key.0);
+    assert_eq!(hash, PipelineKey(0xfa4e_5329_209e_5d44));
+    assert_eq!(hash.to_string(), "fa4e5329209e5d44");
+}
+
+#[test]
+fn lru_eviction() {
+    let mut map = LruMap::new(2);
+    map.insert(b"a".to_vec(), 1);
+    map.insert(b"b".to_vec(), 2);
+    assert_eq!(map.get(b"a"), Some(&1));
+    map.insert(b"c".to_vec(), 3);
+    assert!(map.contains(b"a"));
+    assert!(!map.contains(b"b"));
+    assert_eq!(map.values_by_recency(), [&3, &1]);
+
+    map.insert(b"a".to_vec(), 4);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.values_by_recency(), [&4, &3]);
+
+    map.retain(|&value| value != 4);
+    assert_eq!(map.values_by_recency(), [&3]);
+
+    let mut map = LruMap::new(0);
+    map.insert(b"a".to_vec(), 1);
+    assert!(map.is_empty());
+}
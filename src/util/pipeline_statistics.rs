@@ -0,0 +1,178 @@
+use std::{convert::TryInto, fmt, future::Future};
+
+use crate::{
+    Buffer, BufferAddress, BufferAsyncError, BufferDescriptor, BufferUsage, CommandEncoder, Device,
+    MapMode, PipelineStatisticsTypes, QuerySet, QuerySetDescriptor, QueryType, QUERY_SIZE,
+};
+
+/// Results of a pipeline statistics query.
+///
+/// The statistics which weren't enabled in the query set are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PipelineStatistics {
+    /// Number of times the vertex shader was invoked.
+    pub vertex_shader_invocations: Option<u64>,
+    /// Number of primitives processed by the clipper.
+    pub clipper_invocations: Option<u64>,
+    /// Number of primitives output by the clipper.
+    pub clipper_primitives_out: Option<u64>,
+    /// Number of times the fragment shader was invoked.
+    pub fragment_shader_invocations: Option<u64>,
+    /// Number of times the compute shader was invoked.
+    pub compute_shader_invocations: Option<u64>,
+}
+
+impl PipelineStatistics {
+    /// Size of the results of a query with the `types` statistics, in bytes.
+    pub fn size(types: PipelineStatisticsTypes) -> BufferAddress {
+        types.bits().count_ones() as BufferAddress * QUERY_SIZE as BufferAddress
+    }
+
+    /// Decode the results of a query with the `types` statistics, as resolved into a buffer.
+    ///
+    /// The enabled statistics are laid out one after the other, in the order of their bits.
+    ///
+    /// # Panics
+    ///
+    /// - `data` is shorter than [`PipelineStatistics::size`].
+    pub fn decode(types: PipelineStatisticsTypes, data: &[u8]) -> Self {
+        assert!(
+            data.len() as BufferAddress >= Self::size(types),
+            "Pipeline statistics need {} bytes, got {}",
+            Self::size(types),
+            data.len()
+        );
+        let mut values = data
+            .chunks_exact(QUERY_SIZE as usize)
+            .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()));
+        let mut next = |ty| {
+            if types.contains(ty) {
+                values.next()
+            } else {
+                None
+            }
+        };
+        Self {
+            vertex_shader_invocations: next(PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS),
+            clipper_invocations: next(PipelineStatisticsTypes::CLIPPER_INVOCATIONS),
+            clipper_primitives_out: next(PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT),
+            fragment_shader_invocations: next(PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS),
+            compute_shader_invocations: next(PipelineStatisticsTypes::COMPUTE_SHADER_INVOCATIONS),
+        }
+    }
+}
+
+/// Pipeline statistics query set, along with the buffer its results are read back from.
+///
+/// Queries are recorded with `begin_pipeline_statistics_query` on the passes, using
+/// [`query_set`](Self::query_set). Once [`resolve`](Self::resolve) is submitted, the results
+/// are read back and decoded with [`read_back`](Self::read_back).
+///
+/// [`Features::PIPELINE_STATISTICS_QUERY`](crate::Features::PIPELINE_STATISTICS_QUERY) must
+/// be enabled on the device.
+pub struct PipelineStatisticsQueries {
+    query_set: QuerySet,
+    buffer: Buffer,
+    types: PipelineStatisticsTypes,
+    count: u32,
+}
+
+impl fmt::Debug for PipelineStatisticsQueries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineStatisticsQueries")
+            .field("types", &self.types)
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+impl PipelineStatisticsQueries {
+    /// Create a query set of `count` queries of the `types` statistics, and its readback
+    /// buffer.
+    ///
+    /// # Panics
+    ///
+    /// - `types` is empty.
+    /// - `count` is 0.
+    pub fn new(device: &Device, types: PipelineStatisticsTypes, count: u32) -> Self {
+        assert!(!types.is_empty(), "No pipeline statistics to query");
+        assert!(count > 0, "The query set needs at least one query");
+        Self {
+            query_set: device.create_query_set(&QuerySetDescriptor {
+                count,
+                ty: QueryType::PipelineStatistics(types),
+            }),
+            buffer: device.create_buffer(&BufferDescriptor {
+                label: Some("pipeline statistics"),
+                size: count as BufferAddress * PipelineStatistics::size(types),
+                usage: BufferUsage::COPY_DST | BufferUsage::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            types,
+            count,
+        }
+    }
+
+    /// The query set to record the queries into.
+    pub fn query_set(&self) -> &QuerySet {
+        &self.query_set
+    }
+
+    /// Statistics gathered by the queries.
+    pub fn types(&self) -> PipelineStatisticsTypes {
+        self.types
+    }
+
+    /// Number of queries in the query set.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Resolve all the queries into the readback buffer.
+    pub fn resolve(&self, encoder: &mut CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..self.count, &self.buffer, 0);
+    }
+
+    /// Read back the results of all the queries, in order.
+    ///
+    /// This has to be called after the command buffer recording [`resolve`](Self::resolve)
+    /// is submitted, and the device has to be polled for the future to complete.
+    pub fn read_back(
+        &self,
+    ) -> impl Future<Output = Result<Vec<PipelineStatistics>, BufferAsyncError>> + '_ {
+        let slice = self.buffer.slice(..);
+        let mapping = slice.map_async(MapMode::Read);
+        async move {
+            mapping.await?;
+            let statistics = {
+                let view = slice.get_mapped_range();
+                view.chunks_exact(PipelineStatistics::size(self.types) as usize)
+                    .map(|data| PipelineStatistics::decode(self.types, data))
+                    .collect()
+            };
+            self.buffer.unmap();
+            Ok(statistics)
+        }
+    }
+}
+
+#[test]
+fn pipeline_statistics_follow_enabled_bits() {
+    let data = [7u64, 11, 13]
+        .iter()
+        .flat_map(|value| value.to_ne_bytes().to_vec())
+        .collect::<Vec<_>>();
+    let types = PipelineStatisticsTypes::COMPUTE_SHADER_INVOCATIONS
+        | PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+        | PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS;
+    assert_eq!(PipelineStatistics::size(types), 24);
+    assert_eq!(
+        PipelineStatistics::decode(types, &data),
+        PipelineStatistics {
+            vertex_shader_invocations: Some(7),
+            fragment_shader_invocations: Some(11),
+            compute_shader_invocations: Some(13),
+            ..Default::default()
+        }
+    );
+}
@@ -0,0 +1,481 @@
+use std::{
+    collections::VecDeque,
+    convert::TryInto,
+    env,
+    fmt::{self, Write as _},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{poll_now, MapFuture};
+
+use crate::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsage, CommandEncoder, ComputePass, Device,
+    Features, MapMode, OwnedComputePass, OwnedRenderPass, QuerySet, QuerySetDescriptor, QueryType,
+    Queue, RenderPass, QUERY_SIZE,
+};
+
+/// Encoder able to write timestamps, with [`Features::TIMESTAMP_QUERY`].
+///
+/// It is implemented by [`CommandEncoder`] and by the render and compute passes, to be used
+/// with [`GpuProfiler`].
+pub trait TimestampEncoder {
+    /// Write a timestamp into `query_set`, at `query_index`.
+    fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32);
+}
+
+macro_rules! impl_timestamp_encoder {
+    ($($ty:ty),*) => {$(
+        impl TimestampEncoder for $ty {
+            fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32) {
+                <$ty>::write_timestamp(self, query_set, query_index);
+            }
+        }
+    )*};
+}
+
+impl_timestamp_encoder!(
+    CommandEncoder,
+    RenderPass<'_>,
+    ComputePass<'_>,
+    OwnedRenderPass<'_>,
+    OwnedComputePass<'_>
+);
+
+/// Timing of a scope recorded by [`GpuProfiler`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuTimerScope {
+    /// Label given to [`GpuProfiler::begin_scope`].
+    pub label: String,
+    /// GPU time at which the scope began, in nanoseconds.
+    ///
+    /// GPU times can only be compared to each other: their origin is not specified.
+    pub start_ns: f64,
+    /// GPU time at which the scope ended, in nanoseconds.
+    pub end_ns: f64,
+    /// Scopes recorded within this one, in the order they began.
+    pub nested_scopes: Vec<GpuTimerScope>,
+}
+
+impl GpuTimerScope {
+    /// Time spent in the scope, in nanoseconds.
+    pub fn duration_ns(&self) -> f64 {
+        self.end_ns - self.start_ns
+    }
+}
+
+struct PendingScope {
+    label: String,
+    parent: Option<usize>,
+    begin_query: u32,
+    end_query: u32,
+}
+
+/// Queries of a frame, along with the buffer they are resolved into.
+struct Frame {
+    query_set: QuerySet,
+    buffer: Buffer,
+    scopes: Vec<PendingScope>,
+    query_count: u32,
+    resolved: bool,
+}
+
+/// Profiler measuring the GPU time spent in nested scopes, with timestamp queries.
+///
+/// Scopes are recorded with [`begin_scope`](Self::begin_scope) and
+/// [`end_scope`](Self::end_scope) on command encoders and passes. Once all the scopes of a
+/// frame are recorded, the queries are resolved with
+/// [`resolve_queries`](Self::resolve_queries), and after the command buffers are submitted,
+/// the frame is closed with [`end_frame`](Self::end_frame). Once the device is polled, the
+/// timings are available from [`process_finished_frame`](Self::process_finished_frame), a few
+/// frames later.
+///
+/// Each frame uses its own query set, which is reused once its timings have been read back.
+/// Scopes beyond the capacity of a frame are not measured.
+///
+/// Without [`Features::TIMESTAMP_QUERY`], the profiler does nothing and returns no timings.
+///
+/// ```no_run
+/// # fn example(device: &wgpu::Device, queue: &wgpu::Queue) {
+/// let mut profiler = wgpu::util::GpuProfiler::new(device, queue, 64);
+/// let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+///     label: None,
+/// });
+/// profiler.begin_scope("frame", &mut encoder, device);
+/// {
+///     let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+///         label: None,
+///     });
+///     profiler.begin_scope("simulation", &mut pass, device);
+///     // record the simulation
+///     profiler.end_scope(&mut pass);
+/// }
+/// profiler.end_scope(&mut encoder);
+/// profiler.resolve_queries(&mut encoder);
+/// queue.submit(Some(encoder.finish()));
+/// profiler.end_frame();
+///
+/// device.poll(wgpu::Maintain::Poll);
+/// while let Some(scopes) = profiler.process_finished_frame() {
+///     for scope in &scopes {
+///         println!("{}: {} ns", scope.label, scope.duration_ns());
+///     }
+/// }
+/// # }
+/// ```
+pub struct GpuProfiler {
+    enabled: bool,
+    timestamp_period: f32,
+    max_queries: u32,
+    current: Option<Frame>,
+    open_scopes: Vec<Option<usize>>,
+    free_frames: Vec<Frame>,
+    /// Frames being read back, oldest first.
+    pending_frames: VecDeque<(Frame, MapFuture)>,
+}
+
+impl fmt::Debug for GpuProfiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpuProfiler")
+            .field("enabled", &self.enabled)
+            .field("timestamp_period", &self.timestamp_period)
+            .field("max_queries", &self.max_queries)
+            .field("open_scopes", &self.open_scopes.len())
+            .field("pending_frames", &self.pending_frames.len())
+            .finish()
+    }
+}
+
+impl GpuProfiler {
+    /// Create a profiler measuring up to `max_scopes_per_frame` scopes in each frame.
+    ///
+    /// The profiler is disabled if `device` doesn't have [`Features::TIMESTAMP_QUERY`].
+    ///
+    /// # Panics
+    ///
+    /// - `max_scopes_per_frame` is 0.
+    pub fn new(device: &Device, queue: &Queue, max_scopes_per_frame: u32) -> Self {
+        assert!(
+            max_scopes_per_frame > 0,
+            "GpuProfiler needs room for at least one scope"
+        );
+        let enabled = device.features().contains(Features::TIMESTAMP_QUERY);
+        Self {
+            enabled,
+            timestamp_period: if enabled {
+                queue.get_timestamp_period()
+            } else {
+                0.0
+            },
+            max_queries: max_scopes_per_frame
+                .saturating_mul(2)
+                .min(crate::QUERY_SET_MAX_QUERIES),
+            current: None,
+            open_scopes: Vec::new(),
+            free_frames: Vec::new(),
+            pending_frames: VecDeque::new(),
+        }
+    }
+
+    /// Returns `true` if the device supports timestamp queries, so that scopes are measured.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn current_frame(&mut self, device: &Device) -> &mut Frame {
+        if self.current.is_none() {
+            let frame = self.free_frames.pop().unwrap_or_else(|| Frame {
+                query_set: device.create_query_set(&QuerySetDescriptor {
+                    count: self.max_queries,
+                    ty: QueryType::Timestamp,
+                }),
+                buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("profiler timestamps"),
+                    size: self.max_queries as BufferAddress * QUERY_SIZE as BufferAddress,
+                    usage: BufferUsage::COPY_DST | BufferUsage::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                scopes: Vec::new(),
+                query_count: 0,
+                resolved: false,
+            });
+            self.current = Some(frame);
+        }
+        self.current.as_mut().unwrap()
+    }
+
+    /// Begin a scope named `label`, nested in the scopes which are still open.
+    ///
+    /// The scope can end on a different encoder or pass than the one it began on, as long as
+    /// they are submitted in the same frame.
+    ///
+    /// # Panics
+    ///
+    /// - The queries of the frame were already resolved.
+    pub fn begin_scope<E: TimestampEncoder>(
+        &mut self,
+        label: &str,
+        encoder: &mut E,
+        device: &Device,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let parent = self.open_scopes.last().copied().flatten();
+        let max_queries = self.max_queries;
+        let frame = self.current_frame(device);
+        assert!(
+            !frame.resolved,
+            "Scopes can't begin after the queries of the frame are resolved"
+        );
+        // The end of a scope is written right after its beginning in the query set
+        let index = if frame.query_count + 2 <= max_queries {
+            encoder.write_timestamp(&frame.query_set, frame.query_count);
+            frame.scopes.push(PendingScope {
+                label: label.to_string(),
+                parent,
+                begin_query: frame.query_count,
+                end_query: frame.query_count + 1,
+            });
+            frame.query_count += 2;
+            Some(frame.scopes.len() - 1)
+        } else {
+            None
+        };
+        self.open_scopes.push(index);
+    }
+
+    /// End the innermost open scope.
+    ///
+    /// # Panics
+    ///
+    /// - There is no open scope.
+    pub fn end_scope<E: TimestampEncoder>(&mut self, encoder: &mut E) {
+        if !self.enabled {
+            return;
+        }
+        let index = self
+            .open_scopes
+            .pop()
+            .expect("No scope to end in the profiler");
+        if let (Some(index), Some(frame)) = (index, self.current.as_ref()) {
+            encoder.write_timestamp(&frame.query_set, frame.scopes[index].end_query);
+        }
+    }
+
+    /// Resolve the queries of the frame into its readback buffer.
+    ///
+    /// This has to be recorded after all the scopes of the frame, and before
+    /// [`end_frame`](Self::end_frame).
+    ///
+    /// # Panics
+    ///
+    /// - A scope is still open.
+    pub fn resolve_queries(&mut self, encoder: &mut CommandEncoder) {
+        assert!(
+            self.open_scopes.is_empty(),
+            "All the scopes have to end before the queries are resolved"
+        );
+        if let Some(frame) = self.current.as_mut() {
+            if !frame.resolved && frame.query_count > 0 {
+                encoder.resolve_query_set(&frame.query_set, 0..frame.query_count, &frame.buffer, 0);
+            }
+            frame.resolved = true;
+        }
+    }
+
+    /// Close the frame, and start reading back its timings.
+    ///
+    /// This has to be called after the command buffers resolving the queries are submitted!
+    /// The timings are available from [`process_finished_frame`](Self::process_finished_frame)
+    /// once the device has been polled past their submission.
+    ///
+    /// # Panics
+    ///
+    /// - The queries of the frame weren't resolved.
+    pub fn end_frame(&mut self) {
+        if let Some(frame) = self.current.take() {
+            assert!(
+                frame.resolved,
+                "The queries have to be resolved before the end of the frame"
+            );
+            let mapping = Box::pin(frame.buffer.slice(..).map_async(MapMode::Read));
+            self.pending_frames.push_back((frame, mapping));
+        }
+    }
+
+    /// Returns the timings of the oldest frame which has been read back, if any.
+    ///
+    /// The scopes which began outside of any other are returned in the order they began.
+    pub fn process_finished_frame(&mut self) -> Option<Vec<GpuTimerScope>> {
+        loop {
+            let (_, mapping) = self.pending_frames.front_mut()?;
+            let result = poll_now(mapping)?;
+            let (frame, _) = self.pending_frames.pop_front().unwrap();
+            // A failed mapping drops the frame, so its timings are lost
+            if result.is_ok() {
+                return Some(self.read_frame(frame));
+            }
+        }
+    }
+
+    fn read_frame(&mut self, mut frame: Frame) -> Vec<GpuTimerScope> {
+        let timestamps = {
+            let view = frame.buffer.slice(..).get_mapped_range();
+            view.chunks_exact(QUERY_SIZE as usize)
+                .take(frame.query_count as usize)
+                .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        frame.buffer.unmap();
+
+        let scopes = build_scopes(&frame.scopes, &timestamps, self.timestamp_period);
+        frame.scopes.clear();
+        frame.query_count = 0;
+        frame.resolved = false;
+        self.free_frames.push(frame);
+        scopes
+    }
+
+    /// Write `scopes` to `path` in the Chrome trace event format, to be opened in
+    /// `chrome://tracing`.
+    ///
+    /// GPU times don't share their origin with CPU times, so the GPU timings are written to
+    /// their own file. [`GpuProfiler::chrome_trace_path`] gives one next to the CPU trace.
+    pub fn write_chrome_trace(path: impl AsRef<Path>, scopes: &[GpuTimerScope]) -> io::Result<()> {
+        fs::write(path, chrome_trace(scopes))
+    }
+
+    /// Path of the GPU trace matching the CPU trace set by the `WGPU_CHROME_TRACE` environment
+    /// variable, if it is set.
+    ///
+    /// When `WGPU_CHROME_TRACE` is a directory, this is `gpu_trace.json` inside of it. Otherwise
+    /// it names the CPU trace file, and this is the file next to it with a `.gpu.json`
+    /// extension.
+    pub fn chrome_trace_path() -> Option<PathBuf> {
+        let trace = PathBuf::from(env::var_os("WGPU_CHROME_TRACE")?);
+        let is_dir = trace.is_dir();
+        Some(gpu_trace_path(trace, is_dir))
+    }
+}
+
+/// Derive the GPU trace path from the CPU trace path given to `WGPU_CHROME_TRACE`.
+fn gpu_trace_path(trace: PathBuf, is_dir: bool) -> PathBuf {
+    if is_dir {
+        trace.join("gpu_trace.json")
+    } else {
+        trace.with_extension("gpu.json")
+    }
+}
+
+/// Arrange the scopes of a frame into trees, using their resolved timestamps.
+fn build_scopes(scopes: &[PendingScope], timestamps: &[u64], period: f32) -> Vec<GpuTimerScope> {
+    let to_ns = |query: u32| timestamps[query as usize] as f64 * period as f64;
+    let mut built = scopes
+        .iter()
+        .map(|scope| {
+            Some(GpuTimerScope {
+                label: scope.label.clone(),
+                start_ns: to_ns(scope.begin_query),
+                end_ns: to_ns(scope.end_query),
+                nested_scopes: Vec::new(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Scopes come after their parent, so they are complete when moved into it
+    let mut roots = Vec::new();
+    for (index, scope) in scopes.iter().enumerate().rev() {
+        let mut built_scope = built[index].take().unwrap();
+        built_scope.nested_scopes.reverse();
+        match scope.parent {
+            Some(parent) => built[parent]
+                .as_mut()
+                .unwrap()
+                .nested_scopes
+                .push(built_scope),
+            None => roots.push(built_scope),
+        }
+    }
+    roots.reverse();
+    roots
+}
+
+/// Format `scopes` as a Chrome trace.
+fn chrome_trace(scopes: &[GpuTimerScope]) -> String {
+    fn write_events(trace: &mut String, scopes: &[GpuTimerScope], first: &mut bool) {
+        for scope in scopes {
+            if !*first {
+                trace.push_str(",\n");
+            }
+            *first = false;
+            trace.push_str("{\"name\":\"");
+            for c in scope.label.chars() {
+                match c {
+                    '"' => trace.push_str("\\\""),
+                    '\\' => trace.push_str("\\\\"),
+                    c if c.is_control() => write!(trace, "\\u{:04x}", c as u32).unwrap(),
+                    c => trace.push(c),
+                }
+            }
+            write!(
+                trace,
+                "\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":{},\"tid\":\"GPU\",\"ts\":{},\"dur\":{}}}",
+                std::process::id(),
+                scope.start_ns / 1000.0,
+                scope.duration_ns() / 1000.0,
+            )
+            .unwrap();
+            write_events(trace, &scope.nested_scopes, first);
+        }
+    }
+
+    let mut trace = String::from("[\n");
+    write_events(&mut trace, scopes, &mut true);
+    trace.push_str("\n]\n");
+    trace
+}
+
+#[test]
+fn profiler_scopes_nest_and_trace() {
+    let pending = |label: &str, parent, begin_query| PendingScope {
+        label: label.to_string(),
+        parent,
+        begin_query,
+        end_query: begin_query + 1,
+    };
+    let scopes = [
+        pending("frame", None, 0),
+        pending("shadow", Some(0), 2),
+        pending("main", Some(0), 4),
+        pending("ui \"overlay\"", None, 6),
+    ];
+    let timestamps = [0, 100, 10, 40, 40, 90, 100, 120];
+    let built = build_scopes(&scopes, &timestamps, 2.0);
+
+    assert_eq!(built.len(), 2);
+    assert_eq!(built[0].duration_ns(), 200.0);
+    let nested = built[0]
+        .nested_scopes
+        .iter()
+        .map(|scope| (scope.label.as_str(), scope.start_ns, scope.end_ns))
+        .collect::<Vec<_>>();
+    assert_eq!(nested, [("shadow", 20.0, 80.0), ("main", 80.0, 180.0)]);
+
+    let trace = chrome_trace(&built);
+    assert_eq!(trace.matches("\"ph\":\"X\"").count(), 4);
+    assert!(trace.contains("\"name\":\"ui \\\"overlay\\\"\""));
+    assert!(trace.contains("\"ts\":0.2,\"dur\":0.04"));
+}
+
+#[test]
+fn gpu_trace_paths() {
+    assert_eq!(
+        gpu_trace_path(PathBuf::from("traces"), true),
+        Path::new("traces/gpu_trace.json")
+    );
+    assert_eq!(
+        gpu_trace_path(PathBuf::from("traces/run.json"), false),
+        Path::new("traces/run.gpu.json")
+    );
+}
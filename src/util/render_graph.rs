@@ -0,0 +1,979 @@
+use std::{cmp::Reverse, collections::BinaryHeap, error, fmt};
+
+use crate::{
+    Buffer, BufferDescriptor, Color, CommandBuffer, CommandEncoder, CommandEncoderDescriptor,
+    ComputePass, ComputePassDescriptor, Device, LoadOp, Operations, RenderPass,
+    RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+    RenderPassDescriptor, Texture, TextureDescriptor, TextureView, TextureViewDescriptor,
+};
+
+/// Version of a texture of a [`RenderGraph`].
+///
+/// Each pass writing to a texture returns a new handle, which the passes reading its result
+/// must use. This is what orders the passes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle {
+    index: usize,
+    version: u32,
+}
+
+/// Version of a buffer of a [`RenderGraph`].
+///
+/// Each pass writing to a buffer returns a new handle, which the passes reading its result
+/// must use. This is what orders the passes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BufferHandle {
+    index: usize,
+    version: u32,
+}
+
+/// Identifier of a pass of a [`RenderGraph`], in the order the passes were added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassId(usize);
+
+/// Error detected when compiling a [`RenderGraph`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderGraphError {
+    /// The pass writes to an outdated version of a resource, which another pass already wrote.
+    StaleHandle { pass: String },
+    /// The pass reads a transient resource which no pass wrote before.
+    ReadBeforeWrite { pass: String },
+    /// The pass declares attachments but doesn't record a render pass.
+    AttachmentsOutsideRenderPass { pass: String },
+    /// The dependencies between the passes form a cycle.
+    Cycle,
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StaleHandle { pass } => write!(
+                f,
+                "pass {:?} writes to a resource version which was already written",
+                pass
+            ),
+            Self::ReadBeforeWrite { pass } => write!(
+                f,
+                "pass {:?} reads a transient resource before it is written",
+                pass
+            ),
+            Self::AttachmentsOutsideRenderPass { pass } => write!(
+                f,
+                "pass {:?} declares attachments but doesn't record a render pass",
+                pass
+            ),
+            Self::Cycle => write!(f, "the render graph passes depend on each other in a cycle"),
+        }
+    }
+}
+
+impl error::Error for RenderGraphError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Resource {
+    Texture(usize),
+    Buffer(usize),
+}
+
+enum TextureSource<'a> {
+    Imported(&'a TextureView),
+    Transient(TextureDescriptor<'a>),
+}
+
+enum BufferSource<'a> {
+    Imported(&'a Buffer),
+    Transient(BufferDescriptor<'a>),
+}
+
+struct ResourceNode<S> {
+    source: S,
+    /// Pass producing each version, `None` for the initial contents.
+    producers: Vec<Option<usize>>,
+}
+
+impl<S> ResourceNode<S> {
+    fn new(source: S) -> Self {
+        Self {
+            source,
+            producers: vec![None],
+        }
+    }
+}
+
+struct ColorAttachment {
+    texture: TextureHandle,
+    resolve_target: Option<TextureHandle>,
+    ops: Operations<Color>,
+}
+
+struct DepthStencilAttachment {
+    texture: TextureHandle,
+    depth_ops: Option<Operations<f32>>,
+    stencil_ops: Option<Operations<u32>>,
+}
+
+type RecordRender<'a> = Box<dyn for<'r> FnOnce(&'r GraphResources<'a>, &mut RenderPass<'r>) + 'a>;
+type RecordCompute<'a> = Box<dyn for<'r> FnOnce(&'r GraphResources<'a>, &mut ComputePass<'r>) + 'a>;
+type RecordEncoder<'a> = Box<dyn for<'r> FnOnce(&'r GraphResources<'a>, &mut CommandEncoder) + 'a>;
+
+enum Record<'a> {
+    Render(RecordRender<'a>),
+    Compute(RecordCompute<'a>),
+    Encoder(RecordEncoder<'a>),
+}
+
+struct Write {
+    resource: Resource,
+    version: u32,
+    /// The previous contents are entirely overwritten, like a cleared attachment.
+    discard: bool,
+}
+
+struct PassNode<'a> {
+    name: String,
+    reads: Vec<(Resource, u32)>,
+    writes: Vec<Write>,
+    color_attachments: Vec<ColorAttachment>,
+    depth_stencil_attachment: Option<DepthStencilAttachment>,
+    side_effects: bool,
+    record: Option<Record<'a>>,
+}
+
+/// Frame graph ordering passes by the resources they read and write.
+///
+/// Passes declare the textures and buffers they read and write, and a closure recording their
+/// commands. Resources are either imported, like the swap chain view, or transient, in which
+/// case the graph creates them for the duration of [`execute`](Self::execute).
+///
+/// When compiled, the graph:
+/// - sorts the passes so that each one runs after the passes producing what it reads,
+///   keeping the order of addition otherwise,
+/// - culls the passes whose results are not used by a pass writing an imported resource or
+///   marked with [`PassBuilder::side_effects`],
+/// - aliases transient resources with identical descriptors whose lifetimes don't overlap.
+///
+/// [`compile`](Self::compile) does all of this without a device, which makes it possible to
+/// check the plan in tests.
+///
+/// ```no_run
+/// # fn example(device: &wgpu::Device, queue: &wgpu::Queue, frame: &wgpu::TextureView,
+/// #     gbuffer_desc: &wgpu::TextureDescriptor) {
+/// use wgpu::util::RenderGraph;
+///
+/// let mut graph = RenderGraph::new();
+/// let output = graph.import_texture(frame);
+/// let gbuffer = graph.create_texture(gbuffer_desc);
+///
+/// let mut pass = graph.add_pass("gbuffer");
+/// let gbuffer = pass.color_attachment(gbuffer, wgpu::Operations::default());
+/// pass.render(|_, render_pass| {
+///     // draw the scene
+/// });
+///
+/// let mut pass = graph.add_pass("lighting");
+/// pass.read_texture(gbuffer);
+/// pass.color_attachment(output, wgpu::Operations::default());
+/// pass.render(|resources, render_pass| {
+///     let _gbuffer_view = resources.texture_view(gbuffer);
+///     // draw a fullscreen triangle
+/// });
+///
+/// queue.submit(Some(graph.execute(device).unwrap()));
+/// # }
+/// ```
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    textures: Vec<ResourceNode<TextureSource<'a>>>,
+    buffers: Vec<ResourceNode<BufferSource<'a>>>,
+    passes: Vec<PassNode<'a>>,
+}
+
+impl fmt::Debug for RenderGraph<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderGraph")
+            .field("textures", &self.textures.len())
+            .field("buffers", &self.buffers.len())
+            .field(
+                "passes",
+                &self.passes.iter().map(|p| &p.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Declares the resources used by a pass of a [`RenderGraph`], and how it is recorded.
+///
+/// The pass is added to the graph by [`render`](Self::render), [`compute`](Self::compute)
+/// or [`encoder`](Self::encoder). Dropping the builder instead discards the pass, and the
+/// handles returned by its writes refer to versions that are never produced.
+#[must_use = "the pass is only added to the graph by `render`, `compute` or `encoder`"]
+pub struct PassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    node: PassNode<'a>,
+}
+
+impl fmt::Debug for PassBuilder<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PassBuilder")
+            .field("name", &self.node.name)
+            .finish()
+    }
+}
+
+impl<'g, 'a> PassBuilder<'g, 'a> {
+    fn write(&mut self, resource: Resource, version: u32, discard: bool) -> u32 {
+        self.node.writes.push(Write {
+            resource,
+            version,
+            discard,
+        });
+        version + 1
+    }
+
+    /// Declare that the pass reads this version of a texture.
+    pub fn read_texture(&mut self, texture: TextureHandle) -> &mut Self {
+        self.node
+            .reads
+            .push((Resource::Texture(texture.index), texture.version));
+        self
+    }
+
+    /// Declare that the pass reads this version of a buffer.
+    pub fn read_buffer(&mut self, buffer: BufferHandle) -> &mut Self {
+        self.node
+            .reads
+            .push((Resource::Buffer(buffer.index), buffer.version));
+        self
+    }
+
+    /// Declare that the pass writes to a texture, returning the version it produces.
+    pub fn write_texture(&mut self, texture: TextureHandle) -> TextureHandle {
+        TextureHandle {
+            index: texture.index,
+            version: self.write(Resource::Texture(texture.index), texture.version, false),
+        }
+    }
+
+    /// Declare that the pass writes to a buffer, returning the version it produces.
+    pub fn write_buffer(&mut self, buffer: BufferHandle) -> BufferHandle {
+        BufferHandle {
+            index: buffer.index,
+            version: self.write(Resource::Buffer(buffer.index), buffer.version, false),
+        }
+    }
+
+    /// Render to a color attachment, returning the version of the texture it produces.
+    pub fn color_attachment(
+        &mut self,
+        texture: TextureHandle,
+        ops: Operations<Color>,
+    ) -> TextureHandle {
+        self.resolved_color_attachment(texture, None, ops).0
+    }
+
+    /// Render to a multisampled color attachment resolved to `resolve_target`, returning the
+    /// versions of both textures the pass produces.
+    pub fn resolved_color_attachment(
+        &mut self,
+        texture: TextureHandle,
+        resolve_target: Option<TextureHandle>,
+        ops: Operations<Color>,
+    ) -> (TextureHandle, Option<TextureHandle>) {
+        let discard = match ops.load {
+            LoadOp::Clear(_) => true,
+            LoadOp::Load => false,
+        };
+        let written = TextureHandle {
+            index: texture.index,
+            version: self.write(Resource::Texture(texture.index), texture.version, discard),
+        };
+        let resolved = resolve_target.map(|target| TextureHandle {
+            index: target.index,
+            version: self.write(Resource::Texture(target.index), target.version, true),
+        });
+        self.node.color_attachments.push(ColorAttachment {
+            texture: written,
+            resolve_target: resolved,
+            ops,
+        });
+        (written, resolved)
+    }
+
+    /// Use a depth and/or stencil attachment, returning the version of the texture the
+    /// pass produces.
+    pub fn depth_stencil_attachment(
+        &mut self,
+        texture: TextureHandle,
+        depth_ops: Option<Operations<f32>>,
+        stencil_ops: Option<Operations<u32>>,
+    ) -> TextureHandle {
+        let loads = depth_ops.map_or(false, |ops| ops.load == LoadOp::Load)
+            || stencil_ops.map_or(false, |ops| ops.load == LoadOp::Load);
+        let written = TextureHandle {
+            index: texture.index,
+            version: self.write(Resource::Texture(texture.index), texture.version, !loads),
+        };
+        self.node.depth_stencil_attachment = Some(DepthStencilAttachment {
+            texture: written,
+            depth_ops,
+            stencil_ops,
+        });
+        written
+    }
+
+    /// Never cull the pass, because it has effects outside the graph, for example on a query
+    /// set or on a resource which isn't imported.
+    pub fn side_effects(&mut self) -> &mut Self {
+        self.node.side_effects = true;
+        self
+    }
+
+    fn finish(mut self, record: Record<'a>) -> PassId {
+        self.node.record = Some(record);
+        self.graph.passes.push(self.node);
+        PassId(self.graph.passes.len() - 1)
+    }
+
+    /// Record the pass as a render pass on the declared attachments.
+    pub fn render(
+        self,
+        record: impl for<'r> FnOnce(&'r GraphResources<'a>, &mut RenderPass<'r>) + 'a,
+    ) -> PassId {
+        self.finish(Record::Render(Box::new(record)))
+    }
+
+    /// Record the pass as a compute pass.
+    pub fn compute(
+        self,
+        record: impl for<'r> FnOnce(&'r GraphResources<'a>, &mut ComputePass<'r>) + 'a,
+    ) -> PassId {
+        self.finish(Record::Compute(Box::new(record)))
+    }
+
+    /// Record the pass directly on the command encoder, for example for copies.
+    pub fn encoder(
+        self,
+        record: impl for<'r> FnOnce(&'r GraphResources<'a>, &mut CommandEncoder) + 'a,
+    ) -> PassId {
+        self.finish(Record::Encoder(Box::new(record)))
+    }
+}
+
+/// Result of compiling a [`RenderGraph`]: the order of the passes and the allocation of
+/// transient resources.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderGraphPlan {
+    order: Vec<PassId>,
+    textures: Vec<Option<usize>>,
+    buffers: Vec<Option<usize>>,
+    texture_count: usize,
+    buffer_count: usize,
+}
+
+impl RenderGraphPlan {
+    /// The passes to execute, in order. Culled passes are left out.
+    pub fn order(&self) -> &[PassId] {
+        &self.order
+    }
+
+    /// Returns `true` if the pass is culled.
+    pub fn is_culled(&self, pass: PassId) -> bool {
+        !self.order.contains(&pass)
+    }
+
+    /// Index of the texture allocated for a transient texture, or `None` if it is imported
+    /// or unused. Textures sharing an index are aliased.
+    pub fn transient_texture(&self, texture: TextureHandle) -> Option<usize> {
+        self.textures[texture.index]
+    }
+
+    /// Index of the buffer allocated for a transient buffer, or `None` if it is imported
+    /// or unused. Buffers sharing an index are aliased.
+    pub fn transient_buffer(&self, buffer: BufferHandle) -> Option<usize> {
+        self.buffers[buffer.index]
+    }
+
+    /// Number of textures allocated for the transient textures.
+    pub fn transient_texture_count(&self) -> usize {
+        self.texture_count
+    }
+
+    /// Number of buffers allocated for the transient buffers.
+    pub fn transient_buffer_count(&self) -> usize {
+        self.buffer_count
+    }
+}
+
+/// Assign an allocation to each transient resource, sharing it between resources which have
+/// equal descriptors and disjoint lifetimes.
+///
+/// `lifetimes` holds the first and last positions where each resource is used, if any.
+fn alias<D: PartialEq>(
+    lifetimes: &[Option<(usize, usize)>],
+    descriptors: &[Option<D>],
+) -> (Vec<Option<usize>>, usize) {
+    let mut order = (0..lifetimes.len())
+        .filter(|&i| lifetimes[i].is_some() && descriptors[i].is_some())
+        .collect::<Vec<_>>();
+    order.sort_by_key(|&i| lifetimes[i]);
+
+    let mut assignment = vec![None; lifetimes.len()];
+    // Descriptor and end of the last lifetime of each allocation
+    let mut allocations: Vec<(&D, usize)> = Vec::new();
+    for i in order {
+        let (first, last) = lifetimes[i].unwrap();
+        let desc = descriptors[i].as_ref().unwrap();
+        let reusable = allocations
+            .iter()
+            .position(|&(other, end)| end < first && other == desc);
+        let index = match reusable {
+            Some(index) => {
+                allocations[index].1 = last;
+                index
+            }
+            None => {
+                allocations.push((desc, last));
+                allocations.len() - 1
+            }
+        };
+        assignment[i] = Some(index);
+    }
+    (assignment, allocations.len())
+}
+
+impl<'a> RenderGraph<'a> {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self {
+            textures: Vec::new(),
+            buffers: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    /// Use a texture view created outside of the graph. Passes writing to it are never culled.
+    pub fn import_texture(&mut self, view: &'a TextureView) -> TextureHandle {
+        self.textures
+            .push(ResourceNode::new(TextureSource::Imported(view)));
+        TextureHandle {
+            index: self.textures.len() - 1,
+            version: 0,
+        }
+    }
+
+    /// Declare a texture created by the graph for the duration of its execution.
+    ///
+    /// The texture is viewed with the default [`TextureViewDescriptor`].
+    pub fn create_texture(&mut self, desc: &TextureDescriptor<'a>) -> TextureHandle {
+        self.textures
+            .push(ResourceNode::new(TextureSource::Transient(desc.clone())));
+        TextureHandle {
+            index: self.textures.len() - 1,
+            version: 0,
+        }
+    }
+
+    /// Use a buffer created outside of the graph. Passes writing to it are never culled.
+    pub fn import_buffer(&mut self, buffer: &'a Buffer) -> BufferHandle {
+        self.buffers
+            .push(ResourceNode::new(BufferSource::Imported(buffer)));
+        BufferHandle {
+            index: self.buffers.len() - 1,
+            version: 0,
+        }
+    }
+
+    /// Declare a buffer created by the graph for the duration of its execution.
+    pub fn create_buffer(&mut self, desc: &BufferDescriptor<'a>) -> BufferHandle {
+        self.buffers
+            .push(ResourceNode::new(BufferSource::Transient(desc.clone())));
+        BufferHandle {
+            index: self.buffers.len() - 1,
+            version: 0,
+        }
+    }
+
+    /// Start declaring a pass.
+    pub fn add_pass<'g>(&'g mut self, name: &str) -> PassBuilder<'g, 'a> {
+        PassBuilder {
+            graph: self,
+            node: PassNode {
+                name: name.to_string(),
+                reads: Vec::new(),
+                writes: Vec::new(),
+                color_attachments: Vec::new(),
+                depth_stencil_attachment: None,
+                side_effects: false,
+                record: None,
+            },
+        }
+    }
+
+    fn producers_mut(&mut self, resource: Resource) -> &mut Vec<Option<usize>> {
+        match resource {
+            Resource::Texture(index) => &mut self.textures[index].producers,
+            Resource::Buffer(index) => &mut self.buffers[index].producers,
+        }
+    }
+
+    fn is_imported(&self, resource: Resource) -> bool {
+        match resource {
+            Resource::Texture(index) => match self.textures[index].source {
+                TextureSource::Imported(_) => true,
+                TextureSource::Transient(_) => false,
+            },
+            Resource::Buffer(index) => match self.buffers[index].source {
+                BufferSource::Imported(_) => true,
+                BufferSource::Transient(_) => false,
+            },
+        }
+    }
+
+    /// Sort and cull the passes, and allocate the transient resources, without a device.
+    pub fn compile(&mut self) -> Result<RenderGraphPlan, RenderGraphError> {
+        // Find the producer of each resource version
+        for resource in self.textures.iter_mut().map(|t| &mut t.producers) {
+            resource.truncate(1);
+        }
+        for resource in self.buffers.iter_mut().map(|b| &mut b.producers) {
+            resource.truncate(1);
+        }
+        for pass_index in 0..self.passes.len() {
+            let pass = &self.passes[pass_index];
+            let is_render = match pass.record {
+                Some(Record::Render(_)) => true,
+                _ => false,
+            };
+            if !is_render
+                && (!pass.color_attachments.is_empty() || pass.depth_stencil_attachment.is_some())
+            {
+                return Err(RenderGraphError::AttachmentsOutsideRenderPass {
+                    pass: pass.name.clone(),
+                });
+            }
+            let writes = pass
+                .writes
+                .iter()
+                .map(|w| (w.resource, w.version))
+                .collect::<Vec<_>>();
+            for (resource, version) in writes {
+                let producers = self.producers_mut(resource);
+                if producers.len() != version as usize + 1 {
+                    return Err(RenderGraphError::StaleHandle {
+                        pass: self.passes[pass_index].name.clone(),
+                    });
+                }
+                producers.push(Some(pass_index));
+            }
+        }
+
+        let producer = |graph: &Self, resource: Resource, version: u32| match resource {
+            Resource::Texture(index) => graph.textures[index].producers[version as usize],
+            Resource::Buffer(index) => graph.buffers[index].producers[version as usize],
+        };
+
+        // Passes which must run before each pass, and the ones whose results it needs
+        let mut before = vec![Vec::new(); self.passes.len()];
+        let mut needs = vec![Vec::new(); self.passes.len()];
+        for (pass_index, pass) in self.passes.iter().enumerate() {
+            for &(resource, version) in pass.reads.iter() {
+                let valid = match resource {
+                    Resource::Texture(index) => {
+                        (version as usize) < self.textures[index].producers.len()
+                    }
+                    Resource::Buffer(index) => {
+                        (version as usize) < self.buffers[index].producers.len()
+                    }
+                };
+                if !valid {
+                    return Err(RenderGraphError::StaleHandle {
+                        pass: pass.name.clone(),
+                    });
+                }
+                match producer(self, resource, version) {
+                    Some(other) => {
+                        before[pass_index].push(other);
+                        needs[pass_index].push(other);
+                    }
+                    None if !self.is_imported(resource) => {
+                        return Err(RenderGraphError::ReadBeforeWrite {
+                            pass: pass.name.clone(),
+                        })
+                    }
+                    None => {}
+                }
+            }
+            for write in pass.writes.iter() {
+                if let Some(other) = producer(self, write.resource, write.version) {
+                    before[pass_index].push(other);
+                    if !write.discard {
+                        needs[pass_index].push(other);
+                    }
+                }
+            }
+        }
+        // A write must also happen after the reads of the version it replaces
+        for (pass_index, pass) in self.passes.iter().enumerate() {
+            for &(resource, version) in pass.reads.iter() {
+                let next = match resource {
+                    Resource::Texture(index) => {
+                        self.textures[index].producers.get(version as usize + 1)
+                    }
+                    Resource::Buffer(index) => {
+                        self.buffers[index].producers.get(version as usize + 1)
+                    }
+                };
+                if let Some(&Some(writer)) = next {
+                    if writer != pass_index {
+                        before[writer].push(pass_index);
+                    }
+                }
+            }
+        }
+
+        // Cull the passes which don't contribute to a root
+        let mut live = vec![false; self.passes.len()];
+        let mut stack = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| {
+                pass.side_effects || pass.writes.iter().any(|w| self.is_imported(w.resource))
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            if !live[index] {
+                live[index] = true;
+                stack.extend(needs[index].iter().cloned());
+            }
+        }
+
+        // Sort the live passes, picking the earliest added pass among the ready ones
+        let mut pending = vec![0usize; self.passes.len()];
+        let mut after = vec![Vec::new(); self.passes.len()];
+        for (index, dependencies) in before.iter().enumerate() {
+            if !live[index] {
+                continue;
+            }
+            for &dependency in dependencies.iter().filter(|&&d| live[d]) {
+                pending[index] += 1;
+                after[dependency].push(index);
+            }
+        }
+        let mut ready = (0..self.passes.len())
+            .filter(|&index| live[index] && pending[index] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::new();
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(PassId(index));
+            for &next in after[index].iter() {
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    ready.push(Reverse(next));
+                }
+            }
+        }
+        if order.len() != live.iter().filter(|&&live| live).count() {
+            return Err(RenderGraphError::Cycle);
+        }
+
+        // Lifetimes of the transient resources, in positions of the sorted passes
+        let mut texture_lifetimes = vec![None; self.textures.len()];
+        let mut buffer_lifetimes = vec![None; self.buffers.len()];
+        for (position, &PassId(index)) in order.iter().enumerate() {
+            let pass = &self.passes[index];
+            let used = pass
+                .reads
+                .iter()
+                .map(|&(resource, _)| resource)
+                .chain(pass.writes.iter().map(|w| w.resource));
+            for resource in used {
+                let lifetime = match resource {
+                    Resource::Texture(index) => &mut texture_lifetimes[index],
+                    Resource::Buffer(index) => &mut buffer_lifetimes[index],
+                };
+                *lifetime = match *lifetime {
+                    Some((first, _)) => Some((first, position)),
+                    None => Some((position, position)),
+                };
+            }
+        }
+        let texture_descriptors = self
+            .textures
+            .iter()
+            .map(|texture| match texture.source {
+                TextureSource::Transient(ref desc) => Some(desc.map_label(|_| ())),
+                TextureSource::Imported(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let buffer_descriptors = self
+            .buffers
+            .iter()
+            .map(|buffer| match buffer.source {
+                BufferSource::Transient(ref desc) => Some(desc.map_label(|_| ())),
+                BufferSource::Imported(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let (textures, texture_count) = alias(&texture_lifetimes, &texture_descriptors);
+        let (buffers, buffer_count) = alias(&buffer_lifetimes, &buffer_descriptors);
+
+        Ok(RenderGraphPlan {
+            order,
+            textures,
+            buffers,
+            texture_count,
+            buffer_count,
+        })
+    }
+
+    /// Compile the graph, create its transient resources and record its passes into a
+    /// command buffer.
+    pub fn execute(mut self, device: &Device) -> Result<CommandBuffer, RenderGraphError> {
+        let plan = self.compile()?;
+
+        // Create each allocation from the descriptor of the first resource using it
+        let mut allocated_textures = (0..plan.texture_count).map(|_| None).collect::<Vec<_>>();
+        for (texture, allocation) in self.textures.iter().zip(plan.textures.iter()) {
+            if let (TextureSource::Transient(ref desc), &Some(allocation)) =
+                (&texture.source, allocation)
+            {
+                if allocated_textures[allocation].is_none() {
+                    let texture = device.create_texture(desc);
+                    let view = texture.create_view(&TextureViewDescriptor::default());
+                    allocated_textures[allocation] = Some((texture, view));
+                }
+            }
+        }
+        let mut allocated_buffers = (0..plan.buffer_count).map(|_| None).collect::<Vec<_>>();
+        for (buffer, allocation) in self.buffers.iter().zip(plan.buffers.iter()) {
+            if let (BufferSource::Transient(ref desc), &Some(allocation)) =
+                (&buffer.source, allocation)
+            {
+                if allocated_buffers[allocation].is_none() {
+                    allocated_buffers[allocation] = Some(device.create_buffer(desc));
+                }
+            }
+        }
+
+        let resources = GraphResources {
+            textures: self
+                .textures
+                .iter()
+                .zip(plan.textures.iter())
+                .map(|(texture, &allocation)| match texture.source {
+                    TextureSource::Imported(view) => Slot::Imported(view),
+                    TextureSource::Transient(_) => allocation.map_or(Slot::Unused, Slot::Transient),
+                })
+                .collect(),
+            buffers: self
+                .buffers
+                .iter()
+                .zip(plan.buffers.iter())
+                .map(|(buffer, &allocation)| match buffer.source {
+                    BufferSource::Imported(buffer) => Slot::Imported(buffer),
+                    BufferSource::Transient(_) => allocation.map_or(Slot::Unused, Slot::Transient),
+                })
+                .collect(),
+            allocated_textures: allocated_textures
+                .into_iter()
+                .map(|allocation| allocation.unwrap())
+                .collect(),
+            allocated_buffers: allocated_buffers
+                .into_iter()
+                .map(|allocation| allocation.unwrap())
+                .collect(),
+        };
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("render graph"),
+        });
+        for &PassId(index) in plan.order() {
+            let pass = &mut self.passes[index];
+            let record = pass.record.take().unwrap();
+            encoder.push_debug_group(&pass.name);
+            match record {
+                Record::Render(record) => {
+                    let color_attachments = pass
+                        .color_attachments
+                        .iter()
+                        .map(|attachment| RenderPassColorAttachmentDescriptor {
+                            attachment: resources.texture_view(attachment.texture),
+                            resolve_target: attachment
+                                .resolve_target
+                                .map(|target| resources.texture_view(target)),
+                            ops: attachment.ops,
+                        })
+                        .collect::<Vec<_>>();
+                    let depth_stencil_attachment =
+                        pass.depth_stencil_attachment.as_ref().map(|attachment| {
+                            RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: resources.texture_view(attachment.texture),
+                                depth_ops: attachment.depth_ops,
+                                stencil_ops: attachment.stencil_ops,
+                            }
+                        });
+                    let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                        label: Some(&pass.name),
+                        color_attachments: &color_attachments,
+                        depth_stencil_attachment,
+                    });
+                    record(&resources, &mut render_pass);
+                }
+                Record::Compute(record) => {
+                    let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                        label: Some(&pass.name),
+                    });
+                    record(&resources, &mut compute_pass);
+                }
+                Record::Encoder(record) => record(&resources, &mut encoder),
+            }
+            encoder.pop_debug_group();
+        }
+        Ok(encoder.finish())
+    }
+}
+
+enum Slot<'a, T> {
+    Imported(&'a T),
+    Transient(usize),
+    Unused,
+}
+
+/// Resources of a [`RenderGraph`] being executed, given to the recording closures.
+pub struct GraphResources<'a> {
+    textures: Vec<Slot<'a, TextureView>>,
+    buffers: Vec<Slot<'a, Buffer>>,
+    allocated_textures: Vec<(Texture, TextureView)>,
+    allocated_buffers: Vec<Buffer>,
+}
+
+impl fmt::Debug for GraphResources<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphResources")
+            .field("textures", &self.textures.len())
+            .field("buffers", &self.buffers.len())
+            .finish()
+    }
+}
+
+impl GraphResources<'_> {
+    /// View of a texture.
+    ///
+    /// # Panics
+    ///
+    /// - The texture is transient and unused by the executed passes.
+    pub fn texture_view(&self, texture: TextureHandle) -> &TextureView {
+        match self.textures[texture.index] {
+            Slot::Imported(view) => view,
+            Slot::Transient(allocation) => &self.allocated_textures[allocation].1,
+            Slot::Unused => panic!("Texture {:?} is not used by the render graph", texture),
+        }
+    }
+
+    /// Buffer of a handle.
+    ///
+    /// # Panics
+    ///
+    /// - The buffer is transient and unused by the executed passes.
+    pub fn buffer(&self, buffer: BufferHandle) -> &Buffer {
+        match self.buffers[buffer.index] {
+            Slot::Imported(buffer) => buffer,
+            Slot::Transient(allocation) => &self.allocated_buffers[allocation],
+            Slot::Unused => panic!("Buffer {:?} is not used by the render graph", buffer),
+        }
+    }
+}
+
+#[cfg(test)]
+fn transient_buffer_desc(size: u64) -> BufferDescriptor<'static> {
+    BufferDescriptor {
+        label: None,
+        size,
+        usage: crate::BufferUsage::STORAGE,
+        mapped_at_creation: false,
+    }
+}
+
+#[test]
+fn render_graph_orders_and_culls() {
+    let mut graph = RenderGraph::new();
+    let a = graph.create_buffer(&transient_buffer_desc(64));
+    let b = graph.create_buffer(&transient_buffer_desc(64));
+    let unused = graph.create_buffer(&transient_buffer_desc(64));
+
+    // Added before its producer, so it must be moved after it
+    let mut pass = graph.add_pass("consume");
+    pass.read_buffer(BufferHandle { version: 1, ..a });
+    pass.side_effects();
+    let consume = pass.compute(|_, _| {});
+
+    let mut pass = graph.add_pass("produce");
+    pass.write_buffer(a);
+    let produce = pass.compute(|_, _| {});
+
+    let mut pass = graph.add_pass("dead");
+    pass.write_buffer(unused);
+    let dead = pass.compute(|_, _| {});
+
+    let mut pass = graph.add_pass("other");
+    pass.write_buffer(b);
+    pass.side_effects();
+    let other = pass.compute(|_, _| {});
+
+    let plan = graph.compile().unwrap();
+    assert_eq!(plan.order(), &[produce, consume, other]);
+    assert!(plan.is_culled(dead));
+    assert_eq!(plan.transient_buffer(unused), None);
+    // `a` is dead once `consume` ran, so `b` reuses its allocation
+    assert_eq!(plan.transient_buffer(a), plan.transient_buffer(b));
+    assert_eq!(plan.transient_buffer_count(), 1);
+}
+
+#[test]
+fn render_graph_errors() {
+    let mut graph = RenderGraph::new();
+    let a = graph.create_buffer(&transient_buffer_desc(64));
+    let mut pass = graph.add_pass("read");
+    pass.read_buffer(a);
+    pass.compute(|_, _| {});
+    assert_eq!(
+        graph.compile(),
+        Err(RenderGraphError::ReadBeforeWrite {
+            pass: "read".to_string()
+        })
+    );
+
+    let mut graph = RenderGraph::new();
+    let a = graph.create_buffer(&transient_buffer_desc(64));
+    let mut pass = graph.add_pass("first");
+    pass.write_buffer(a);
+    pass.compute(|_, _| {});
+    let mut pass = graph.add_pass("second");
+    pass.write_buffer(a);
+    pass.compute(|_, _| {});
+    assert_eq!(
+        graph.compile(),
+        Err(RenderGraphError::StaleHandle {
+            pass: "second".to_string()
+        })
+    );
+}
+
+#[test]
+fn render_graph_dropped_pass() {
+    // The write of a dropped builder is never produced
+    let mut graph = RenderGraph::new();
+    let a = graph.create_buffer(&transient_buffer_desc(64));
+    let written = graph.add_pass("dropped").write_buffer(a);
+    let mut pass = graph.add_pass("read");
+    pass.read_buffer(written);
+    pass.compute(|_, _| {});
+    assert_eq!(
+        graph.compile(),
+        Err(RenderGraphError::StaleHandle {
+            pass: "read".to_string()
+        })
+    );
+}
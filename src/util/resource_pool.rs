@@ -0,0 +1,443 @@
+use std::{collections::VecDeque, error, fmt, sync::Arc};
+
+use super::{poll_now, MapFuture};
+
+use crate::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsage, CommandBuffer, Device, MapMode, Queue,
+    Texture, TextureDescriptor, TextureDimension,
+};
+
+/// Error returned by [`ResourcePool`] when a resource can't be handed out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResourcePoolError {
+    /// Creating the resource would exceed the memory budget of the pool, even after freeing
+    /// all the unused resources.
+    BudgetExceeded {
+        /// Estimated size of the resource, in bytes.
+        requested: BufferAddress,
+        /// Memory used by the pool, in bytes.
+        usage: BufferAddress,
+        /// Budget of the pool, in bytes.
+        budget: BufferAddress,
+    },
+}
+
+impl fmt::Display for ResourcePoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BudgetExceeded {
+                requested,
+                usage,
+                budget,
+            } => write!(
+                f,
+                "allocating {} bytes with {} bytes in use exceeds the budget of {} bytes",
+                requested, usage, budget
+            ),
+        }
+    }
+}
+
+impl error::Error for ResourcePoolError {}
+
+/// Estimated memory used by a texture, in bytes.
+fn texture_size(desc: &TextureDescriptor) -> BufferAddress {
+    let format_info = desc.format.describe();
+    let (layers, extent) = if desc.dimension == TextureDimension::D3 {
+        (1, desc.size)
+    } else {
+        (
+            desc.size.depth,
+            crate::Extent3d {
+                depth: 1,
+                ..desc.size
+            },
+        )
+    };
+    let mut size = 0;
+    for mip in 0..desc.mip_level_count.min(u8::MAX as u32) as u8 {
+        let mip_size = match extent.at_mip_level(mip) {
+            Some(mip_size) => mip_size.physical_size(desc.format),
+            None => break,
+        };
+        let width_blocks = mip_size.width / format_info.block_dimensions.0 as u32;
+        let height_blocks = mip_size.height / format_info.block_dimensions.1 as u32;
+        size += width_blocks as BufferAddress
+            * height_blocks as BufferAddress
+            * mip_size.depth as BufferAddress
+            * format_info.block_size as BufferAddress;
+    }
+    size * layers as BufferAddress * desc.sample_count as BufferAddress
+}
+
+struct Entry<D, T> {
+    desc: D,
+    resource: Arc<T>,
+    size: BufferAddress,
+    /// Epoch at which the resource was last handed out.
+    acquired: u64,
+    /// Frame at which the resource was last handed out.
+    last_used_frame: u64,
+}
+
+impl<D, T> Entry<D, T> {
+    /// The GPU is done with the submissions since the resource was handed out, and the caller
+    /// released it.
+    fn is_available(&self, completed_epoch: u64) -> bool {
+        self.acquired < completed_epoch && Arc::strong_count(&self.resource) == 1
+    }
+}
+
+/// Pool of textures and buffers, reusing them between resources with identical descriptors.
+///
+/// Resources are handed out as `Arc`s. Once dropped by the caller, a resource goes back to
+/// the pool when the GPU is done with the commands using it. The command buffers using pooled
+/// resources are submitted with [`submit`](Self::submit), which tracks their completion, and
+/// the device has to be polled for it to be noticed. Frames are marked by
+/// [`end_frame`](Self::end_frame), and resources which weren't handed out for
+/// `max_unused_frames` frames are freed.
+///
+/// The memory held by the pool is estimated from the descriptors. When a budget is set,
+/// unused resources are freed to make room for new ones, and creating a resource which
+/// doesn't fit returns an error.
+///
+/// Debug labels are not part of the key: a resource keeps the label it was created with.
+/// Buffers can't be mapped at creation, as they wouldn't be when reused.
+pub struct ResourcePool {
+    max_unused_frames: u64,
+    budget: Option<BufferAddress>,
+    usage: BufferAddress,
+    frame: u64,
+    epoch: u64,
+    /// All the submissions before this epoch are completed.
+    completed_epoch: u64,
+    /// Fences of the submissions in flight, oldest first, with their epoch.
+    pending_fences: VecDeque<(u64, Buffer, MapFuture)>,
+    /// Fences of completed submissions, to be reused.
+    free_fences: Vec<Buffer>,
+    textures: Vec<Entry<TextureDescriptor<'static>, Texture>>,
+    buffers: Vec<Entry<BufferDescriptor<'static>, Buffer>>,
+}
+
+impl fmt::Debug for ResourcePool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourcePool")
+            .field("max_unused_frames", &self.max_unused_frames)
+            .field("budget", &self.budget)
+            .field("usage", &self.usage)
+            .field("frame", &self.frame)
+            .field("epoch", &self.epoch)
+            .field("completed_epoch", &self.completed_epoch)
+            .field("pending_fences", &self.pending_fences.len())
+            .field("textures", &self.textures.len())
+            .field("buffers", &self.buffers.len())
+            .finish()
+    }
+}
+
+impl ResourcePool {
+    /// Create an empty pool, freeing the resources unused for `max_unused_frames` frames.
+    pub fn new(max_unused_frames: u32) -> Self {
+        Self {
+            max_unused_frames: max_unused_frames as u64,
+            budget: None,
+            usage: 0,
+            frame: 0,
+            epoch: 0,
+            completed_epoch: 0,
+            pending_fences: VecDeque::new(),
+            free_fences: Vec::new(),
+            textures: Vec::new(),
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Memory budget of the pool, in bytes.
+    pub fn budget(&self) -> Option<BufferAddress> {
+        self.budget
+    }
+
+    /// Set the memory budget of the pool, in bytes, or remove it with `None`.
+    ///
+    /// Unused resources are freed until the pool fits the budget. Resources in use are kept,
+    /// so the pool may still exceed it.
+    pub fn set_budget(&mut self, budget: Option<BufferAddress>) {
+        self.budget = budget;
+        if let Some(budget) = budget {
+            self.free_until(budget);
+        }
+    }
+
+    /// Estimated memory held by the pool, in bytes, whether the resources are in use or not.
+    pub fn memory_usage(&self) -> BufferAddress {
+        self.usage
+    }
+
+    /// Number of textures held by the pool.
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Number of buffers held by the pool.
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Free unused resources, least recently used first, until the usage is at most `target`.
+    fn free_until(&mut self, target: BufferAddress) {
+        self.receive_completions();
+        while self.usage > target {
+            let epoch = self.completed_epoch;
+            let oldest_texture = self
+                .textures
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.is_available(epoch))
+                .min_by_key(|(_, entry)| entry.last_used_frame)
+                .map(|(index, entry)| (entry.last_used_frame, index));
+            let oldest_buffer = self
+                .buffers
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.is_available(epoch))
+                .min_by_key(|(_, entry)| entry.last_used_frame)
+                .map(|(index, entry)| (entry.last_used_frame, index));
+            let freed = match (oldest_texture, oldest_buffer) {
+                (Some((texture_frame, index)), Some((buffer_frame, _)))
+                    if texture_frame <= buffer_frame =>
+                {
+                    self.textures.swap_remove(index).size
+                }
+                (Some((_, index)), None) => self.textures.swap_remove(index).size,
+                (_, Some((_, index))) => self.buffers.swap_remove(index).size,
+                (None, None) => break,
+            };
+            self.usage -= freed;
+        }
+    }
+
+    /// Make room for a new resource of `size` bytes.
+    fn reserve(&mut self, size: BufferAddress) -> Result<(), ResourcePoolError> {
+        if let Some(budget) = self.budget {
+            self.free_until(budget.saturating_sub(size));
+            if self.usage + size > budget {
+                return Err(ResourcePoolError::BudgetExceeded {
+                    requested: size,
+                    usage: self.usage,
+                    budget,
+                });
+            }
+        }
+        self.usage += size;
+        Ok(())
+    }
+
+    /// Hand out a texture matching `desc`, reusing an available one if possible.
+    pub fn acquire_texture(
+        &mut self,
+        device: &Device,
+        desc: &TextureDescriptor,
+    ) -> Result<Arc<Texture>, ResourcePoolError> {
+        let key = desc.map_label(|_| None);
+        self.receive_completions();
+        let (epoch, completed_epoch, frame) = (self.epoch, self.completed_epoch, self.frame);
+        if let Some(entry) = self
+            .textures
+            .iter_mut()
+            .find(|entry| entry.desc == key && entry.is_available(completed_epoch))
+        {
+            entry.acquired = epoch;
+            entry.last_used_frame = frame;
+            return Ok(Arc::clone(&entry.resource));
+        }
+
+        let size = texture_size(desc);
+        self.reserve(size)?;
+        let resource = Arc::new(device.create_texture(desc));
+        self.textures.push(Entry {
+            desc: key,
+            resource: Arc::clone(&resource),
+            size,
+            acquired: epoch,
+            last_used_frame: frame,
+        });
+        Ok(resource)
+    }
+
+    /// Hand out a buffer matching `desc`, reusing an available one if possible.
+    ///
+    /// # Panics
+    ///
+    /// - `desc.mapped_at_creation` is `true`.
+    pub fn acquire_buffer(
+        &mut self,
+        device: &Device,
+        desc: &BufferDescriptor,
+    ) -> Result<Arc<Buffer>, ResourcePoolError> {
+        assert!(
+            !desc.mapped_at_creation,
+            "Pooled buffers can't be mapped at creation"
+        );
+        let key = desc.map_label(|_| None);
+        self.receive_completions();
+        let (epoch, completed_epoch, frame) = (self.epoch, self.completed_epoch, self.frame);
+        if let Some(entry) = self
+            .buffers
+            .iter_mut()
+            .find(|entry| entry.desc == key && entry.is_available(completed_epoch))
+        {
+            entry.acquired = epoch;
+            entry.last_used_frame = frame;
+            return Ok(Arc::clone(&entry.resource));
+        }
+
+        self.reserve(desc.size)?;
+        let resource = Arc::new(device.create_buffer(desc));
+        self.buffers.push(Entry {
+            desc: key,
+            resource: Arc::clone(&resource),
+            size: desc.size,
+            acquired: epoch,
+            last_used_frame: frame,
+        });
+        Ok(resource)
+    }
+
+    fn receive_completions(&mut self) {
+        while let Some((_, _, mapping)) = self.pending_fences.front_mut() {
+            let result = match poll_now(mapping) {
+                Some(result) => result,
+                None => break,
+            };
+            let (epoch, fence, _) = self.pending_fences.pop_front().unwrap();
+            self.completed_epoch = self.completed_epoch.max(epoch + 1);
+            // A failed mapping drops the fence, as the submission is over either way
+            if result.is_ok() {
+                fence.unmap();
+                self.free_fences.push(fence);
+            }
+        }
+    }
+
+    /// Submit `command_buffers` to `queue`, marking the resources handed out so far as used by
+    /// them.
+    ///
+    /// A fence is written along with the command buffers, and the resources are available again
+    /// once released and the device has been polled past the submission. Command buffers using
+    /// pooled resources must be submitted with this rather than [`Queue::submit`].
+    pub fn submit<I: IntoIterator<Item = CommandBuffer>>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        command_buffers: I,
+    ) {
+        self.receive_completions();
+        let epoch = self.epoch;
+        self.epoch += 1;
+
+        let fence = self.free_fences.pop().unwrap_or_else(|| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some("ResourcePool fence"),
+                size: 4,
+                usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        // Buffer writes are part of the next submission, so the fence completes with it
+        queue.write_buffer(&fence, 0, &[0; 4]);
+        queue.submit(command_buffers);
+        let mapping = Box::pin(fence.slice(..).map_async(MapMode::Read));
+        self.pending_fences.push_back((epoch, fence, mapping));
+    }
+
+    /// Mark the end of a frame: the resources unused for `max_unused_frames` frames are freed.
+    pub fn end_frame(&mut self) {
+        self.receive_completions();
+        self.frame += 1;
+
+        let (epoch, frame, max_unused_frames) =
+            (self.completed_epoch, self.frame, self.max_unused_frames);
+        let is_stale = |acquired, last_used_frame, strong_count| {
+            acquired < epoch && strong_count == 1 && frame - last_used_frame > max_unused_frames
+        };
+        let mut freed = 0;
+        self.textures.retain(|entry| {
+            let stale = is_stale(
+                entry.acquired,
+                entry.last_used_frame,
+                Arc::strong_count(&entry.resource),
+            );
+            if stale {
+                freed += entry.size;
+            }
+            !stale
+        });
+        self.buffers.retain(|entry| {
+            let stale = is_stale(
+                entry.acquired,
+                entry.last_used_frame,
+                Arc::strong_count(&entry.resource),
+            );
+            if stale {
+                freed += entry.size;
+            }
+            !stale
+        });
+        self.usage -= freed;
+    }
+
+    /// Free all the resources which are not in use.
+    pub fn trim(&mut self) {
+        self.free_until(0);
+    }
+}
+
+#[test]
+fn texture_size_counts_mips_and_blocks() {
+    let desc = TextureDescriptor {
+        label: None,
+        size: crate::Extent3d {
+            width: 4,
+            height: 4,
+            depth: 2,
+        },
+        mip_level_count: 3,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: crate::TextureFormat::Rgba8Unorm,
+        usage: crate::TextureUsage::SAMPLED,
+    };
+    assert_eq!(texture_size(&desc), (64 + 16 + 4) * 2);
+
+    // Compressed mips are rounded up to whole blocks
+    let desc = TextureDescriptor {
+        size: crate::Extent3d {
+            width: 6,
+            height: 6,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        format: crate::TextureFormat::Bc1RgbaUnorm,
+        ..desc
+    };
+    assert_eq!(texture_size(&desc), 4 * 8);
+}
+
+#[test]
+fn entries_wait_for_completion() {
+    let mut entry = Entry {
+        desc: (),
+        resource: Arc::new(0u32),
+        size: 4,
+        acquired: 2,
+        last_used_frame: 0,
+    };
+    assert!(!entry.is_available(2));
+    assert!(entry.is_available(3));
+
+    let resource = Arc::clone(&entry.resource);
+    assert!(!entry.is_available(3));
+    drop(resource);
+    entry.acquired = 3;
+    assert!(!entry.is_available(3));
+}
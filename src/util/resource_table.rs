@@ -0,0 +1,197 @@
+use std::{num::NonZeroU32, sync::Arc};
+
+use crate::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Device, Features, ShaderStage,
+    TextureSampleType, TextureView, TextureViewDimension,
+};
+
+/// Describes a [`ResourceTable`].
+#[derive(Clone, Debug)]
+pub struct ResourceTableDescriptor<'a> {
+    /// Debug label of the bind group layout and of the bind groups.
+    pub label: Option<&'a str>,
+    /// Number of slots of the table.
+    pub capacity: NonZeroU32,
+    /// Shader stages which can index the table.
+    pub visibility: ShaderStage,
+    /// Sample type of the texture views.
+    pub sample_type: TextureSampleType,
+    /// Dimension of the texture views.
+    pub view_dimension: TextureViewDimension,
+    /// View bound to the empty slots. It must match the sample type and dimension of the table.
+    pub placeholder: Arc<TextureView>,
+}
+
+/// Free list of the slots of a table, reusing the most recently freed slot first.
+#[derive(Debug)]
+struct Slots {
+    free: Vec<u32>,
+}
+
+impl Slots {
+    fn new(capacity: u32) -> Self {
+        Self {
+            free: (0..capacity).rev().collect(),
+        }
+    }
+
+    fn allocate(&mut self) -> Option<u32> {
+        self.free.pop()
+    }
+
+    fn free(&mut self, slot: u32) {
+        self.free.push(slot);
+    }
+}
+
+/// Table of texture views bound as a single binding array, for bindless rendering.
+///
+/// Each view gets a stable `u32` slot which shaders use to index the array, and freed slots
+/// are reused. Empty slots are filled with a placeholder view. The bind group is rebuilt
+/// lazily by [`bind_group`](Self::bind_group) after the slots change, so it should be
+/// fetched again after modifying the table.
+///
+/// The device must support [`Features::SAMPLED_TEXTURE_BINDING_ARRAY`], along with
+/// [`Features::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING`] to index the array with a uniform value,
+/// or [`Features::SAMPLED_TEXTURE_ARRAY_NON_UNIFORM_INDEXING`] when the index varies between
+/// invocations, as in the `texture-arrays` example. The array is at binding 0 of the layout.
+#[derive(Debug)]
+pub struct ResourceTable {
+    label: Option<String>,
+    layout: BindGroupLayout,
+    placeholder: Arc<TextureView>,
+    views: Vec<Option<Arc<TextureView>>>,
+    slots: Slots,
+    bind_group: Option<BindGroup>,
+}
+
+impl ResourceTable {
+    /// Create an empty table, with its bind group layout.
+    ///
+    /// # Panics
+    ///
+    /// - The device doesn't support [`Features::SAMPLED_TEXTURE_BINDING_ARRAY`].
+    pub fn new(device: &Device, desc: &ResourceTableDescriptor) -> Self {
+        assert!(
+            device
+                .features()
+                .contains(Features::SAMPLED_TEXTURE_BINDING_ARRAY),
+            "ResourceTable requires Features::SAMPLED_TEXTURE_BINDING_ARRAY"
+        );
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: desc.label,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: desc.visibility,
+                ty: BindingType::Texture {
+                    sample_type: desc.sample_type,
+                    view_dimension: desc.view_dimension,
+                    multisampled: false,
+                },
+                count: Some(desc.capacity),
+            }],
+        });
+        let capacity = desc.capacity.get();
+        Self {
+            label: desc.label.map(str::to_string),
+            layout,
+            placeholder: Arc::clone(&desc.placeholder),
+            views: vec![None; capacity as usize],
+            slots: Slots::new(capacity),
+            bind_group: None,
+        }
+    }
+
+    /// Layout of the bind groups of the table, to use in pipeline layouts.
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    /// Number of slots of the table.
+    pub fn capacity(&self) -> u32 {
+        self.views.len() as u32
+    }
+
+    /// Number of occupied slots.
+    pub fn len(&self) -> usize {
+        self.views.len() - self.slots.free.len()
+    }
+
+    /// Returns `true` if no slot is occupied.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Put a view in a free slot, returning the slot, or `None` if the table is full.
+    pub fn insert(&mut self, view: Arc<TextureView>) -> Option<u32> {
+        let slot = self.slots.allocate()?;
+        self.views[slot as usize] = Some(view);
+        self.bind_group = None;
+        Some(slot)
+    }
+
+    /// Replace the view of an occupied slot, returning the previous one.
+    ///
+    /// # Panics
+    ///
+    /// - The slot is empty.
+    pub fn replace(&mut self, slot: u32, view: Arc<TextureView>) -> Arc<TextureView> {
+        let previous = self.views[slot as usize]
+            .replace(view)
+            .expect("Replacing an empty slot of a resource table");
+        self.bind_group = None;
+        previous
+    }
+
+    /// Free a slot, returning its view. The slot can be handed out again by
+    /// [`insert`](Self::insert).
+    pub fn remove(&mut self, slot: u32) -> Option<Arc<TextureView>> {
+        let view = self.views.get_mut(slot as usize)?.take()?;
+        self.slots.free(slot);
+        self.bind_group = None;
+        Some(view)
+    }
+
+    /// View of an occupied slot.
+    pub fn get(&self, slot: u32) -> Option<&Arc<TextureView>> {
+        self.views.get(slot as usize)?.as_ref()
+    }
+
+    /// Bind group of the table, rebuilt if the slots changed since the last call.
+    pub fn bind_group(&mut self, device: &Device) -> &BindGroup {
+        let Self {
+            ref label,
+            ref layout,
+            ref placeholder,
+            ref views,
+            ref mut bind_group,
+            ..
+        } = *self;
+        bind_group.get_or_insert_with(|| {
+            let array = views
+                .iter()
+                .map(|view| &**view.as_ref().unwrap_or(placeholder))
+                .collect::<Vec<_>>();
+            device.create_bind_group(&BindGroupDescriptor {
+                label: label.as_deref(),
+                layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureViewArray(&array),
+                }],
+            })
+        })
+    }
+}
+
+#[test]
+fn slots_reuse_freed() {
+    let mut slots = Slots::new(3);
+    assert_eq!(slots.allocate(), Some(0));
+    assert_eq!(slots.allocate(), Some(1));
+    slots.free(0);
+    assert_eq!(slots.allocate(), Some(0));
+    assert_eq!(slots.allocate(), Some(2));
+    assert_eq!(slots.allocate(), None);
+}
@@ -0,0 +1,180 @@
+use std::{
+    borrow::Cow,
+    error, fmt,
+    mem::{align_of, size_of},
+    ptr::copy_nonoverlapping,
+};
+
+use crate::ShaderSource;
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+const SWAPPED_MAGIC_NUMBER: u32 = 0x0302_2307;
+const HEADER_WORDS: usize = 5;
+
+/// Error returned by [`try_make_spirv`] when the data is not a SPIR-V binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpirvError {
+    /// The length of the data, in bytes, isn't a multiple of 4.
+    InvalidLength(usize),
+    /// The data is too short to contain the 5 words of the SPIR-V header.
+    MissingHeader(usize),
+    /// The first word is neither the SPIR-V magic number nor its byte-swapped version.
+    InvalidMagicNumber(u32),
+}
+
+impl fmt::Display for SpirvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(len) => write!(f, "data size {} is not a multiple of 4", len),
+            Self::MissingHeader(len) => {
+                write!(f, "data size {} is too small to hold a SPIR-V header", len)
+            }
+            Self::InvalidMagicNumber(word) => write!(
+                f,
+                "wrong magic word {:x}. Make sure you are using a binary SPIRV file.",
+                word
+            ),
+        }
+    }
+}
+
+impl error::Error for SpirvError {}
+
+/// Header of a SPIR-V module, useful for diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpirvHeader {
+    /// Major and minor version of the SPIR-V specification the module targets.
+    pub version: (u8, u8),
+    /// Magic number of the tool which generated the module.
+    pub generator: u32,
+    /// Upper bound of all the IDs used in the module.
+    pub bound: u32,
+    /// Whether the module was stored with the opposite endianness.
+    pub swapped: bool,
+}
+
+impl SpirvHeader {
+    /// Read the header of a SPIR-V binary, in either endianness.
+    pub fn read(data: &[u8]) -> Result<Self, SpirvError> {
+        check_length(data)?;
+        let word = |index: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&data[index * 4..index * 4 + 4]);
+            u32::from_ne_bytes(bytes)
+        };
+        let swapped = match word(0) {
+            MAGIC_NUMBER => false,
+            SWAPPED_MAGIC_NUMBER => true,
+            other => return Err(SpirvError::InvalidMagicNumber(other)),
+        };
+        let read = |index| {
+            if swapped {
+                word(index).swap_bytes()
+            } else {
+                word(index)
+            }
+        };
+        let version = read(1);
+        Ok(Self {
+            version: ((version >> 16) as u8, (version >> 8) as u8),
+            generator: read(2),
+            bound: read(3),
+            swapped,
+        })
+    }
+}
+
+fn check_length(data: &[u8]) -> Result<(), SpirvError> {
+    if data.len() % size_of::<u32>() != 0 {
+        return Err(SpirvError::InvalidLength(data.len()));
+    }
+    if data.len() < HEADER_WORDS * size_of::<u32>() {
+        return Err(SpirvError::MissingHeader(data.len()));
+    }
+    Ok(())
+}
+
+/// Treat the given byte slice as a SPIR-V module, returning an error if it isn't one.
+///
+/// Modules stored with the opposite endianness are detected and converted,
+/// which requires a copy of the data. Use [`SpirvHeader::read`] to inspect the header.
+pub fn try_make_spirv(data: &[u8]) -> Result<ShaderSource, SpirvError> {
+    let header = SpirvHeader::read(data)?;
+
+    //If the data happens to be aligned, directly use the byte array,
+    // otherwise copy the byte array in an owned vector and use that instead.
+    let mut words = if data.as_ptr().align_offset(align_of::<u32>()) == 0 {
+        let (pre, words, post) = unsafe { data.align_to::<u32>() };
+        debug_assert!(pre.is_empty());
+        debug_assert!(post.is_empty());
+        Cow::from(words)
+    } else {
+        let mut words = vec![0u32; data.len() / size_of::<u32>()];
+        unsafe {
+            copy_nonoverlapping(data.as_ptr(), words.as_mut_ptr() as *mut u8, data.len());
+        }
+        Cow::from(words)
+    };
+
+    if header.swapped {
+        for word in words.to_mut().iter_mut() {
+            *word = word.swap_bytes();
+        }
+    }
+    Ok(ShaderSource::SpirV(words))
+}
+
+/// Treat the given byte slice as a SPIR-V module.
+///
+/// # Panic
+///
+/// This function panics if:
+///
+/// - Input length isn't multiple of 4
+/// - Input is too short to contain a SPIR-V header
+/// - SPIR-V magic number is missing from beginning of stream
+///
+/// See [`try_make_spirv`] for a version which returns an error instead.
+pub fn make_spirv(data: &[u8]) -> ShaderSource {
+    match try_make_spirv(data) {
+        Ok(source) => source,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+#[test]
+fn test_try_make_spirv() {
+    let words = [MAGIC_NUMBER, 0x0001_0300, 0x0008_0001, 12, 0];
+    let mut bytes = Vec::new();
+    for word in words.iter() {
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let header = SpirvHeader::read(&bytes).unwrap();
+    assert_eq!(header.version, (1, 3));
+    assert_eq!(header.generator, 0x0008_0001);
+    assert_eq!(header.bound, 12);
+    assert!(!header.swapped);
+
+    let mut swapped = Vec::new();
+    for word in words.iter() {
+        swapped.extend_from_slice(&word.swap_bytes().to_ne_bytes());
+    }
+    assert_eq!(SpirvHeader::read(&swapped).unwrap().bound, 12);
+    match try_make_spirv(&swapped).unwrap() {
+        ShaderSource::SpirV(converted) => assert_eq!(&converted[..], &words[..]),
+        _ => unreachable!(),
+    }
+
+    assert_eq!(
+        try_make_spirv(&bytes[..7]).err(),
+        Some(SpirvError::InvalidLength(7))
+    );
+    assert_eq!(
+        try_make_spirv(&bytes[..8]).err(),
+        Some(SpirvError::MissingHeader(8))
+    );
+    assert_eq!(
+        try_make_spirv(&[0; 20]).err(),
+        Some(SpirvError::InvalidMagicNumber(0))
+    );
+}
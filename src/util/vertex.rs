@@ -0,0 +1,51 @@
+use crate::{BufferAddress, InputStepMode, VertexAttribute, VertexBufferLayout};
+
+/// Layout of a Rust type used as an element of a vertex buffer.
+///
+/// It is usually derived, with `#[derive(VertexLayout)]` on a `#[repr(C)]` struct.
+/// Each field becomes an attribute at its offset within the struct, with a format
+/// picked from its type: `f32`, `u32`, `i32` and `f64` or arrays of up to 4 of them,
+/// and arrays of 2 or 4 `u8`, `i8`, `u16` and `i16`. Offsets follow `#[repr(packed)]` and
+/// `#[repr(packed(N))]` as well.
+///
+/// Shader locations start at 0 and increase with each field. The derive accepts these attributes:
+///
+/// - `#[vertex(instance)]` on the struct makes it an instance buffer layout.
+/// - `#[vertex(location = N)]` sets the shader location of a field; the next fields follow it.
+/// - `#[vertex(normalized)]` uses the normalized format of an integer field, e.g. `Uchar4Norm`.
+/// - `#[vertex(format = "Half2")]` sets the format of a field explicitly.
+/// - `#[vertex(skip)]` leaves a field out, for example padding.
+///
+/// ```
+/// use wgpu::util::VertexLayout;
+///
+/// #[repr(C)]
+/// #[derive(Clone, Copy, VertexLayout)]
+/// struct Vertex {
+///     position: [f32; 3],
+///     #[vertex(normalized)]
+///     color: [u8; 4],
+///     #[vertex(location = 4)]
+///     material: u32,
+/// }
+///
+/// let layout = Vertex::layout();
+/// assert_eq!(layout.array_stride, 20);
+/// assert_eq!(layout.attributes[1].offset, 12);
+/// assert_eq!(layout.attributes[2].shader_location, 4);
+/// ```
+pub trait VertexLayout: Sized {
+    /// How often a buffer of this type is stepped forward.
+    const STEP_MODE: InputStepMode;
+    /// Attributes of the fields of the type.
+    const ATTRIBUTES: &'static [VertexAttribute];
+
+    /// Layout of a vertex buffer holding elements of this type.
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: Self::STEP_MODE,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Derive macros for [wgpu](https://docs.rs/wgpu).
+//!
+//! These are re-exported by `wgpu`, use them from there.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr, Fields, Ident,
+    Lit, Meta, NestedMeta, Type,
+};
+
+/// Derive `wgpu::util::VertexLayout` for a `#[repr(C)]` struct.
+///
+/// See the documentation of the trait for the supported attributes.
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex_layout(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct FieldOptions {
+    location: Option<u32>,
+    format: Option<Ident>,
+    normalized: bool,
+    skip: bool,
+}
+
+/// Parse the `#[vertex(...)]` attributes, calling `handle` with each item.
+fn parse_vertex_attributes(
+    attrs: &[Attribute],
+    mut handle: impl FnMut(&NestedMeta) -> syn::Result<()>,
+) -> syn::Result<()> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("vertex")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for item in list.nested.iter() {
+                    handle(item)?;
+                }
+            }
+            meta => return Err(Error::new_spanned(meta, "expected `#[vertex(...)]`")),
+        }
+    }
+    Ok(())
+}
+
+fn field_options(attrs: &[Attribute]) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    parse_vertex_attributes(attrs, |item| {
+        match item {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("normalized") => {
+                options.normalized = true
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => options.skip = true,
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("location") => match nv.lit {
+                Lit::Int(ref int) => options.location = Some(int.base10_parse()?),
+                ref lit => return Err(Error::new_spanned(lit, "expected an integer")),
+            },
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("format") => match nv.lit {
+                Lit::Str(ref string) => options.format = Some(string.parse()?),
+                ref lit => return Err(Error::new_spanned(lit, "expected a string")),
+            },
+            _ => {
+                return Err(Error::new_spanned(
+                    item,
+                    "expected `location = N`, `format = \"...\"`, `normalized` or `skip`",
+                ))
+            }
+        }
+        Ok(())
+    })?;
+    Ok(options)
+}
+
+/// Returns the scalar type name and the component count of a field type.
+fn scalar_components(ty: &Type) -> Option<(String, usize)> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => Some((path.path.get_ident()?.to_string(), 1)),
+        Type::Array(array) => {
+            let count = match array.len {
+                Expr::Lit(ref lit) => match lit.lit {
+                    Lit::Int(ref int) => int.base10_parse().ok()?,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            match scalar_components(&array.elem)? {
+                (scalar, 1) => Some((scalar, count)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Pick the vertex format matching a field type.
+fn vertex_format(ty: &Type, normalized: bool) -> Option<&'static str> {
+    let (scalar, count) = scalar_components(ty)?;
+    Some(match (scalar.as_str(), count, normalized) {
+        ("u8", 2, false) => "Uchar2",
+        ("u8", 4, false) => "Uchar4",
+        ("i8", 2, false) => "Char2",
+        ("i8", 4, false) => "Char4",
+        ("u8", 2, true) => "Uchar2Norm",
+        ("u8", 4, true) => "Uchar4Norm",
+        ("i8", 2, true) => "Char2Norm",
+        ("i8", 4, true) => "Char4Norm",
+        ("u16", 2, false) => "Ushort2",
+        ("u16", 4, false) => "Ushort4",
+        ("i16", 2, false) => "Short2",
+        ("i16", 4, false) => "Short4",
+        ("u16", 2, true) => "Ushort2Norm",
+        ("u16", 4, true) => "Ushort4Norm",
+        ("i16", 2, true) => "Short2Norm",
+        ("i16", 4, true) => "Short4Norm",
+        ("f32", 1, false) => "Float",
+        ("f32", 2, false) => "Float2",
+        ("f32", 3, false) => "Float3",
+        ("f32", 4, false) => "Float4",
+        ("u32", 1, false) => "Uint",
+        ("u32", 2, false) => "Uint2",
+        ("u32", 3, false) => "Uint3",
+        ("u32", 4, false) => "Uint4",
+        ("i32", 1, false) => "Int",
+        ("i32", 2, false) => "Int2",
+        ("i32", 3, false) => "Int3",
+        ("i32", 4, false) => "Int4",
+        ("f64", 1, false) => "Double",
+        ("f64", 2, false) => "Double2",
+        ("f64", 3, false) => "Double3",
+        ("f64", 4, false) => "Double4",
+        _ => return None,
+    })
+}
+
+/// Returns whether the struct is `#[repr(C)]`, and the maximum field alignment if it is packed.
+///
+/// `packed` is the same as `packed(1)`.
+fn repr_c(attrs: &[Attribute]) -> syn::Result<(bool, Option<u64>)> {
+    let (mut c, mut packed) = (false, None);
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("repr")) {
+        if let Meta::List(list) = attr.parse_meta()? {
+            for item in list.nested.iter() {
+                match item {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("C") => c = true,
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("packed") => {
+                        packed = Some(1)
+                    }
+                    NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("packed") => {
+                        packed = match list.nested.iter().collect::<Vec<_>>()[..] {
+                            [NestedMeta::Lit(Lit::Int(int))] => Some(int.base10_parse()?),
+                            _ => return Err(Error::new_spanned(list, "expected `packed(N)`")),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok((c, packed))
+}
+
+fn vertex_layout(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "VertexLayout cannot be derived for generic types",
+        ));
+    }
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => fields.named.iter().collect::<Vec<_>>(),
+            Fields::Unnamed(ref fields) => fields.unnamed.iter().collect(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "VertexLayout can only be derived for structs",
+            ))
+        }
+    };
+    let (c, packed) = repr_c(&input.attrs)?;
+    if !c {
+        return Err(Error::new_spanned(
+            input,
+            "VertexLayout requires `#[repr(C)]`, so that the field offsets are well defined",
+        ));
+    }
+
+    let mut step_mode = quote!(Vertex);
+    parse_vertex_attributes(&input.attrs, |item| match item {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("instance") => {
+            step_mode = quote!(Instance);
+            Ok(())
+        }
+        _ => Err(Error::new_spanned(item, "expected `instance`")),
+    })?;
+
+    let mut offsets = Vec::new();
+    let mut attributes = Vec::new();
+    let mut location = 0u32;
+    for (index, field) in fields.iter().enumerate() {
+        let ty = &field.ty;
+        let offset = format_ident!("__offset_{}", index);
+        // Follow the layout rules of `#[repr(C)]`: each field is placed at the end of the
+        // previous one, rounded up to its alignment.
+        let end = match index {
+            0 => quote!(0u64),
+            _ => {
+                let previous = format_ident!("__offset_{}", index - 1);
+                let previous_ty = &fields[index - 1].ty;
+                quote!(#previous + ::std::mem::size_of::<#previous_ty>() as u64)
+            }
+        };
+        // `packed(N)` lowers the alignment of the fields to at most `N`
+        let align = match packed {
+            Some(1) => quote!(1u64),
+            Some(pack) => quote!({
+                let align = ::std::mem::align_of::<#ty>() as u64;
+                if align < #pack {
+                    align
+                } else {
+                    #pack
+                }
+            }),
+            None => quote!(::std::mem::align_of::<#ty>() as u64),
+        };
+        offsets.push(quote! {
+            let #offset = (#end + #align - 1) / #align * #align;
+        });
+
+        let options = field_options(&field.attrs)?;
+        if options.skip {
+            continue;
+        }
+        let format = match options.format {
+            Some(format) => format,
+            None => match vertex_format(ty, options.normalized) {
+                Some(format) => Ident::new(format, ty.span()),
+                None => {
+                    return Err(Error::new_spanned(
+                        ty,
+                        "no vertex format matches this type, specify one with `#[vertex(format = \"...\")]`",
+                    ))
+                }
+            },
+        };
+        if let Some(explicit) = options.location {
+            location = explicit;
+        }
+        attributes.push(quote! {
+            ::wgpu::VertexAttribute {
+                format: ::wgpu::VertexFormat::#format,
+                offset: #offset,
+                shader_location: #location,
+            }
+        });
+        location += 1;
+    }
+
+    Ok(quote! {
+        impl ::wgpu::util::VertexLayout for #name {
+            const STEP_MODE: ::wgpu::InputStepMode = ::wgpu::InputStepMode::#step_mode;
+            const ATTRIBUTES: &'static [::wgpu::VertexAttribute] = {
+                #(#offsets)*
+                &[#(#attributes),*]
+            };
+        }
+    })
+}